@@ -0,0 +1,63 @@
+use crate::grid::{CellGrid, CellType};
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Condition {
+    CellAt { x: i32, y: i32, cell_type: CellType },
+    CountAbove { cell_type: CellType, count: u32 },
+    MaxHeatAbove(f32)
+}
+
+impl Condition {
+    pub fn is_met(&self, cell_grid: &CellGrid) -> bool {
+        match *self {
+            Condition::CellAt { x, y, cell_type } => cell_grid.get_cell(x, y).map(|cell| cell.element_data.cell_type) == Some(cell_type),
+            Condition::CountAbove { cell_type, count } => cell_grid.count_of(cell_type) > count,
+            Condition::MaxHeatAbove(heat) => cell_grid.max_heat() > heat
+        }
+    }
+
+    pub fn kind_name(&self) -> &'static str {
+        match self {
+            Condition::CellAt { .. } => "cell at",
+            Condition::CountAbove { .. } => "count above",
+            Condition::MaxHeatAbove(_) => "max heat above"
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            Condition::CellAt { x, y, cell_type } => format!("{:?} at ({}, {})", cell_type, x, y),
+            Condition::CountAbove { cell_type, count } => format!("{:?} count > {}", cell_type, count),
+            Condition::MaxHeatAbove(heat) => format!("max heat > {}", heat)
+        }
+    }
+}
+
+pub struct Breakpoint {
+    pub condition: Condition,
+    pub enabled: bool,
+    was_met: bool
+}
+
+impl Breakpoint {
+    pub fn new(condition: Condition) -> Self {
+        Self { condition, enabled: true, was_met: false }
+    }
+}
+
+/// Returns the first enabled breakpoint whose condition just became true, so a condition that keeps holding
+/// only pauses the simulation once
+pub fn check(breakpoints: &mut [Breakpoint], cell_grid: &CellGrid) -> Option<Condition> {
+    let mut hit = None;
+
+    for breakpoint in breakpoints.iter_mut().filter(|breakpoint| breakpoint.enabled) {
+        let met = breakpoint.condition.is_met(cell_grid);
+
+        if met && !breakpoint.was_met && hit.is_none() {
+            hit = Some(breakpoint.condition);
+        }
+        breakpoint.was_met = met;
+    }
+
+    hit
+}
@@ -0,0 +1,379 @@
+use std::collections::{HashSet, VecDeque};
+
+use crate::grid::{Cell, CellGrid, CellType, ElementData};
+
+pub const MAX_BRUSH_SIZE: i32 = 50;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BrushShape {
+    Square, Circle, Line, Rectangle, FloodFill
+}
+
+impl BrushShape {
+    pub const ALL: [BrushShape; 5] = [BrushShape::Square, BrushShape::Circle, BrushShape::Line, BrushShape::Rectangle, BrushShape::FloodFill];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            BrushShape::Square => "square",
+            BrushShape::Circle => "circle",
+            BrushShape::Line => "line",
+            BrushShape::Rectangle => "rectangle",
+            BrushShape::FloodFill => "flood fill"
+        }
+    }
+}
+
+/// A single application of a brush, in cell coordinates
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Brush {
+    Square { x: i32, y: i32, size: i32 },
+    Circle { x: i32, y: i32, size: i32 },
+    Line { x0: i32, y0: i32, x1: i32, y1: i32, size: i32 },
+    Rectangle { x0: i32, y0: i32, x1: i32, y1: i32 },
+    FloodFill { x: i32, y: i32 }
+}
+
+impl Brush {
+    /// Positions inside the grid borders covered by the brush, without duplicates
+    pub fn positions(&self, cell_grid: &CellGrid) -> Vec<(i32, i32)> {
+        let mut positions = Vec::new();
+
+        // shapes are clipped to the inside of the grid, a brush far past the edge would otherwise cover a huge area
+        let bounds = cell_grid.interior_bounds();
+
+        match *self {
+            Brush::Square { x, y, size } => square(x, y, size, bounds, &mut positions),
+            Brush::Circle { x, y, size } => circle(x, y, size, bounds, &mut positions),
+            Brush::Line { x0, y0, x1, y1, size } => {
+                let steps = (x1 - x0).abs().max((y1 - y0).abs());
+
+                for step in 0..=steps {
+                    let t = if steps == 0 { 0f32 } else { step as f32 / steps as f32 };
+                    let x = x0 + ((x1 - x0) as f32 * t).round() as i32;
+                    let y = y0 + ((y1 - y0) as f32 * t).round() as i32;
+
+                    circle(x, y, size, bounds, &mut positions);
+                }
+            }
+            Brush::Rectangle { x0, y0, x1, y1 } => {
+                let ((min_i, min_j), (max_i, max_j)) = bounds;
+
+                for i in x0.min(x1).max(min_i)..=x0.max(x1).min(max_i) {
+                    for j in y0.min(y1).max(min_j)..=y0.max(y1).min(max_j) {
+                        positions.push((i, j));
+                    }
+                }
+            }
+            Brush::FloodFill { x, y } => return flood_fill(cell_grid, x, y)
+        }
+
+        let mut seen = HashSet::new();
+        positions.retain(|&(i, j)| cell_grid.in_interior(i, j) && seen.insert((i, j)));
+
+        positions
+    }
+}
+
+/// Which cells a brush is allowed to overwrite
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BrushFilter {
+    ReplaceAll,
+    OnlyAir,
+    OnlyElement(CellType),
+    /// Paints air over cells of the element and leaves everything else alone
+    EraseElement(CellType)
+}
+
+impl BrushFilter {
+    pub fn name(&self) -> String {
+        match self {
+            BrushFilter::ReplaceAll => String::from("replace all"),
+            BrushFilter::OnlyAir => String::from("only replace Air"),
+            BrushFilter::OnlyElement(cell_type) => format!("only replace {:?}", cell_type),
+            BrushFilter::EraseElement(cell_type) => format!("erase only {:?}", cell_type)
+        }
+    }
+
+    fn accepts(&self, cell: &Cell) -> bool {
+        match self {
+            BrushFilter::ReplaceAll => true,
+            BrushFilter::OnlyAir => cell.element_data.cell_type == CellType::Air,
+            BrushFilter::OnlyElement(cell_type) | BrushFilter::EraseElement(cell_type) => cell.element_data.cell_type == *cell_type
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct BrushModifiers {
+    /// Fraction of the covered cells that get painted, 1 paints all of them
+    pub density: f32,
+    pub filter: BrushFilter
+}
+
+impl Default for BrushModifiers {
+    fn default() -> Self {
+        Self { density: 1f32, filter: BrushFilter::ReplaceAll }
+    }
+}
+
+impl BrushModifiers {
+    /// Drops the positions the filter rejects and, when spraying, a random share of the rest
+    pub fn apply(&self, cell_grid: &CellGrid, positions: &mut Vec<(i32, i32)>) {
+        self.apply_with(cell_grid, positions, fastrand::f32);
+    }
+
+    /// `apply` drawing the spray randomness from `random`, which returns values in `0..1`
+    pub fn apply_with(&self, cell_grid: &CellGrid, positions: &mut Vec<(i32, i32)>, mut random: impl FnMut() -> f32) {
+        positions.retain(|&(i, j)| cell_grid.get_cell(i, j).is_some_and(|cell| self.filter.accepts(&cell)));
+
+        if self.density < 1f32 {
+            positions.retain(|_| random() < self.density);
+        }
+    }
+
+    /// The cell that actually gets painted when `selected` is the chosen element
+    pub fn paint_cell(&self, selected: Cell) -> Cell {
+        match self.filter {
+            BrushFilter::EraseElement(_) => Cell::new(ElementData::air_element()),
+            _ => selected
+        }
+    }
+}
+
+/// Lowest and highest position a shape may cover, see `CellGrid::interior_bounds`
+type Bounds = ((i32, i32), (i32, i32));
+
+fn square(x: i32, y: i32, size: i32, ((min_i, min_j), (max_i, max_j)): Bounds, positions: &mut Vec<(i32, i32)>) {
+    let (x0, y0) = (x - size / 2, y - size / 2);
+
+    for i in x0.max(min_i)..=(x0 + size - 1).min(max_i) {
+        for j in y0.max(min_j)..=(y0 + size - 1).min(max_j) {
+            positions.push((i, j));
+        }
+    }
+}
+
+fn circle(x: i32, y: i32, size: i32, ((min_i, min_j), (max_i, max_j)): Bounds, positions: &mut Vec<(i32, i32)>) {
+    let radius = size as f32 / 2f32;
+    let reach = radius.ceil() as i32;
+
+    for i in (x - reach).max(min_i)..=(x + reach).min(max_i) {
+        for j in (y - reach).max(min_j)..=(y + reach).min(max_j) {
+            let (dx, dy) = (i - x, j - y);
+
+            if (dx * dx + dy * dy) as f32 <= radius * radius || (dx == 0 && dy == 0) {
+                positions.push((i, j));
+            }
+        }
+    }
+}
+
+/// Every interior cell 4-connected to `(x, y)` that has the same element
+fn flood_fill(cell_grid: &CellGrid, x: i32, y: i32) -> Vec<(i32, i32)> {
+    let mut positions = Vec::new();
+
+    if !cell_grid.in_interior(x, y) {
+        return positions;
+    }
+
+    let target = cell_grid.get_cell(x, y).map(|cell| cell.element_data.cell_type);
+
+    let mut seen = HashSet::new();
+    let mut queue = VecDeque::new();
+
+    seen.insert((x, y));
+    queue.push_back((x, y));
+
+    while let Some((i, j)) = queue.pop_front() {
+        positions.push((i, j));
+
+        for (ni, nj) in [(i + 1, j), (i - 1, j), (i, j + 1), (i, j - 1)] {
+            if cell_grid.in_interior(ni, nj) && cell_grid.get_cell(ni, nj).map(|cell| cell.element_data.cell_type) == target && seen.insert((ni, nj)) {
+                queue.push_back((ni, nj));
+            }
+        }
+    }
+
+    positions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_unique(positions: &[(i32, i32)]) -> bool {
+        positions.iter().collect::<HashSet<_>>().len() == positions.len()
+    }
+
+    #[test]
+    fn square_covers_size_by_size_cells() {
+        let cell_grid = CellGrid::new(1f32);
+        let positions = Brush::Square { x: 50, y: 50, size: 4 }.positions(&cell_grid);
+
+        assert_eq!(positions.len(), 16);
+        assert!(positions.iter().all(|&(i, j)| (48..=51).contains(&i) && (48..=51).contains(&j)));
+        assert!(is_unique(&positions));
+    }
+
+    #[test]
+    fn circle_covers_cells_within_the_radius() {
+        let cell_grid = CellGrid::new(1f32);
+        let positions = Brush::Circle { x: 50, y: 50, size: 5 }.positions(&cell_grid);
+
+        // the 5x5 block around the center minus its four corners
+        assert_eq!(positions.len(), 21);
+        assert!(positions.contains(&(50, 50)));
+        assert!(positions.iter().all(|&(i, j)| ((i - 50).pow(2) + (j - 50).pow(2)) as f32 <= 2.5 * 2.5));
+
+        assert_eq!(Brush::Circle { x: 50, y: 50, size: 1 }.positions(&cell_grid), vec![(50, 50)]);
+    }
+
+    #[test]
+    fn shapes_are_clipped_to_the_interior() {
+        let cell_grid = CellGrid::new(1f32);
+
+        assert_eq!(Brush::Square { x: 0, y: 0, size: 4 }.positions(&cell_grid), vec![(1, 1)]);
+        assert!(Brush::Circle { x: -100, y: 50, size: 5 }.positions(&cell_grid).is_empty());
+
+        let positions = Brush::Line { x0: -500, y0: 50, x1: 500, y1: 50, size: 1 }.positions(&cell_grid);
+        assert!(positions.iter().all(|&(i, j)| cell_grid.in_interior(i, j)));
+    }
+
+    #[test]
+    fn line_has_no_gaps_between_far_apart_points() {
+        let cell_grid = CellGrid::new(1f32);
+        let positions = Brush::Line { x0: 10, y0: 10, x1: 90, y1: 40, size: 1 }.positions(&cell_grid);
+
+        assert!(positions.contains(&(10, 10)));
+        assert!(positions.contains(&(90, 40)));
+
+        // every covered cell is reachable from the start through neighbouring (including diagonal) covered cells
+        let covered: HashSet<_> = positions.iter().copied().collect();
+        let mut reached = HashSet::from([(10, 10)]);
+        let mut queue = VecDeque::from([(10, 10)]);
+
+        while let Some((i, j)) = queue.pop_front() {
+            for di in -1..=1 {
+                for dj in -1..=1 {
+                    let next = (i + di, j + dj);
+
+                    if covered.contains(&next) && reached.insert(next) {
+                        queue.push_back(next);
+                    }
+                }
+            }
+        }
+
+        assert_eq!(reached.len(), covered.len());
+    }
+
+    #[test]
+    fn overlapping_cells_are_returned_once() {
+        let cell_grid = CellGrid::new(1f32);
+
+        let positions = Brush::Line { x0: 30, y0: 30, x1: 40, y1: 32, size: 6 }.positions(&cell_grid);
+        assert!(is_unique(&positions));
+
+        let point = Brush::Line { x0: 30, y0: 30, x1: 30, y1: 30, size: 3 }.positions(&cell_grid);
+        assert_eq!(point, Brush::Circle { x: 30, y: 30, size: 3 }.positions(&cell_grid));
+    }
+
+    #[test]
+    fn flood_fill_stays_within_connected_cells_of_the_same_element() {
+        let mut cell_grid = CellGrid::new(1f32);
+
+        // a hollow 11x11 ring of sand, with one more grain touching its corner only diagonally
+        for k in 20..=30 {
+            for (i, j) in [(k, 20), (k, 30), (20, k), (30, k)] {
+                cell_grid.set_cell(i, j, Cell::new(ElementData::sand_element()));
+            }
+        }
+        cell_grid.set_cell(31, 31, Cell::new(ElementData::sand_element()));
+
+        let inside = Brush::FloodFill { x: 25, y: 25 }.positions(&cell_grid);
+        assert_eq!(inside.len(), 9 * 9);
+        assert!(inside.iter().all(|&(i, j)| (21..=29).contains(&i) && (21..=29).contains(&j)));
+
+        let ring = Brush::FloodFill { x: 20, y: 25 }.positions(&cell_grid);
+        assert_eq!(ring.len(), 40);
+        assert!(!ring.contains(&(31, 31)));
+        assert!(is_unique(&ring));
+
+        assert!(Brush::FloodFill { x: 0, y: 0 }.positions(&cell_grid).is_empty());
+    }
+
+    /// Sand on the left half of a 20x20 block at (40, 40), air on the right half
+    fn half_sand_grid() -> (CellGrid, Vec<(i32, i32)>) {
+        let mut cell_grid = CellGrid::new(1f32);
+        let block = Brush::Square { x: 50, y: 50, size: 20 }.positions(&cell_grid);
+
+        for &(i, j) in block.iter().filter(|&&(i, _)| i < 50) {
+            cell_grid.set_cell(i, j, Cell::new(ElementData::sand_element()));
+        }
+
+        (cell_grid, block)
+    }
+
+    fn filtered(filter: BrushFilter, density: f32, rng: &fastrand::Rng) -> (CellGrid, Vec<(i32, i32)>) {
+        let (cell_grid, mut positions) = half_sand_grid();
+        BrushModifiers { density, filter }.apply_with(&cell_grid, &mut positions, || rng.f32());
+
+        (cell_grid, positions)
+    }
+
+    fn cell_type(cell_grid: &CellGrid, (i, j): (i32, i32)) -> CellType {
+        cell_grid.get_cell(i, j).unwrap().element_data.cell_type
+    }
+
+    #[test]
+    fn filters_keep_only_the_cells_they_may_overwrite() {
+        let rng = fastrand::Rng::with_seed(7);
+
+        let (_, all) = filtered(BrushFilter::ReplaceAll, 1f32, &rng);
+        assert_eq!(all.len(), 400);
+
+        let (cell_grid, only_air) = filtered(BrushFilter::OnlyAir, 1f32, &rng);
+        assert_eq!(only_air.len(), 200);
+        assert!(only_air.iter().all(|&p| cell_type(&cell_grid, p) == CellType::Air));
+
+        let (cell_grid, only_sand) = filtered(BrushFilter::OnlyElement(CellType::Sand), 1f32, &rng);
+        assert_eq!(only_sand.len(), 200);
+        assert!(only_sand.iter().all(|&p| cell_type(&cell_grid, p) == CellType::Sand));
+
+        let (cell_grid, erase_sand) = filtered(BrushFilter::EraseElement(CellType::Sand), 1f32, &rng);
+        assert_eq!(erase_sand, only_sand);
+        assert!(erase_sand.iter().all(|&p| cell_type(&cell_grid, p) == CellType::Sand));
+
+        let (_, no_water) = filtered(BrushFilter::OnlyElement(CellType::Water), 1f32, &rng);
+        assert!(no_water.is_empty());
+    }
+
+    #[test]
+    fn erase_paints_air_and_other_filters_paint_the_selection() {
+        let sand = Cell::new(ElementData::sand_element());
+
+        for filter in [BrushFilter::ReplaceAll, BrushFilter::OnlyAir, BrushFilter::OnlyElement(CellType::Water)] {
+            let cell = BrushModifiers { density: 1f32, filter }.paint_cell(sand);
+            assert_eq!(cell.element_data.cell_type, CellType::Sand);
+        }
+
+        let erased = BrushModifiers { density: 1f32, filter: BrushFilter::EraseElement(CellType::Water) }.paint_cell(sand);
+        assert_eq!(erased.element_data.cell_type, CellType::Air);
+    }
+
+    #[test]
+    fn density_keeps_a_seeded_share_of_the_filtered_cells() {
+        let (_, sprayed) = filtered(BrushFilter::OnlyAir, 0.25, &fastrand::Rng::with_seed(42));
+        let (_, again) = filtered(BrushFilter::OnlyAir, 0.25, &fastrand::Rng::with_seed(42));
+
+        // the same seed sprays the same cells, and roughly a quarter of the 200 air cells
+        assert_eq!(sprayed, again);
+        assert!((30..=70).contains(&sprayed.len()), "{}", sprayed.len());
+
+        let (cell_grid, _) = half_sand_grid();
+        assert!(sprayed.iter().all(|&p| cell_type(&cell_grid, p) == CellType::Air));
+
+        let (_, none) = filtered(BrushFilter::ReplaceAll, 0f32, &fastrand::Rng::with_seed(42));
+        assert!(none.is_empty());
+    }
+}
@@ -0,0 +1,87 @@
+use crate::engine::shape_renderer::BlendMode;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ColorMap {
+    Classic, Viridis, Inferno, BlueRed
+}
+
+const CLASSIC: [u32; 3] = [0x0000ff, 0x00ff00, 0xff0000];
+const VIRIDIS: [u32; 9] = [0x440154, 0x472d7b, 0x3b528b, 0x2c728e, 0x21918c, 0x28ae80, 0x5ec962, 0xaddc30, 0xfde725];
+const INFERNO: [u32; 9] = [0x000004, 0x1f0c48, 0x550f6d, 0x88226a, 0xba3655, 0xe35933, 0xf98c0a, 0xf9c932, 0xfcffa4];
+const BLUE_RED: [u32; 5] = [0x3b4cc0, 0x8db0fe, 0xdddddd, 0xf49a7b, 0xb40426];
+
+impl ColorMap {
+    pub const ALL: [ColorMap; 4] = [ColorMap::Classic, ColorMap::Viridis, ColorMap::Inferno, ColorMap::BlueRed];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ColorMap::Classic => "classic",
+            ColorMap::Viridis => "viridis",
+            ColorMap::Inferno => "inferno",
+            ColorMap::BlueRed => "blue-red"
+        }
+    }
+
+    /// Diverging maps are centered on 0 when auto ranging
+    pub fn is_diverging(&self) -> bool {
+        matches!(self, ColorMap::Classic | ColorMap::BlueRed)
+    }
+
+    /// Color at `t` in 0..=1 as rgb in 0..=1
+    pub fn sample(&self, t: f32) -> (f32, f32, f32) {
+        let stops: &[u32] = match self {
+            ColorMap::Classic => &CLASSIC,
+            ColorMap::Viridis => &VIRIDIS,
+            ColorMap::Inferno => &INFERNO,
+            ColorMap::BlueRed => &BLUE_RED
+        };
+
+        let position = t.clamp(0f32, 1f32) * (stops.len() - 1) as f32;
+        let index = (position as usize).min(stops.len() - 2);
+        let fraction = position - index as f32;
+
+        let (r0, g0, b0) = unpack(stops[index]);
+        let (r1, g1, b1) = unpack(stops[index + 1]);
+
+        (r0 + (r1 - r0) * fraction, g0 + (g1 - g0) * fraction, b0 + (b1 - b0) * fraction)
+    }
+}
+
+fn unpack(rgb: u32) -> (f32, f32, f32) {
+    (((rgb >> 16) & 0xff) as f32 / 255f32, ((rgb >> 8) & 0xff) as f32 / 255f32, (rgb & 0xff) as f32 / 255f32)
+}
+
+pub struct HeatMapSettings {
+    pub color_map: ColorMap,
+    pub auto_range: bool,
+    pub min: f32,
+    pub max: f32,
+    pub opacity: f32,
+    /// How the overlay is composited over the element view
+    pub blend_mode: BlendMode
+}
+
+impl Default for HeatMapSettings {
+    fn default() -> Self {
+        Self { color_map: ColorMap::Classic, auto_range: false, min: -2000f32, max: 2000f32, opacity: 0.6, blend_mode: BlendMode::Alpha }
+    }
+}
+
+impl HeatMapSettings {
+    /// Fits the range to the heat values currently in the grid
+    pub fn auto_fit(&mut self, min_heat: f32, max_heat: f32) {
+        if self.color_map.is_diverging() {
+            let extent = min_heat.abs().max(max_heat.abs()).max(1f32);
+
+            self.min = -extent;
+            self.max = extent;
+        } else {
+            self.min = min_heat;
+            self.max = max_heat.max(min_heat + 1f32);
+        }
+    }
+
+    pub fn color(&self, heat: f32) -> (f32, f32, f32) {
+        self.color_map.sample((heat - self.min) / (self.max - self.min))
+    }
+}
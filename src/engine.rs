@@ -1,5 +1,9 @@
+#[allow(clippy::module_inception)]
 pub mod engine;
 pub mod camera;
+pub mod error;
 pub mod shader;
 pub mod shape_renderer;
-pub mod triangle;
\ No newline at end of file
+pub mod pixel_renderer;
+pub mod triangle;
+pub mod timestep;
@@ -2,7 +2,7 @@
 
 use std::ops::Add;
 
-use glam::{Mat4, Vec2, Vec3};
+use glam::{Mat4, Vec2, Vec3, Vec4};
 
 pub struct Camera {
     proj_matrix: Mat4,
@@ -14,16 +14,18 @@ pub struct Camera {
     view_width: f32,
     view_height: f32,
 
-    pub position: Vec2
+    zoom: f32,
+
+    position: Vec2
 }
 
+pub const MIN_ZOOM: f32 = 0.25;
+pub const MAX_ZOOM: f32 = 16.0;
+
 impl Camera {
     pub fn new(x: f32, y: f32, view_width: f32, view_height: f32) -> Self {
-        let proj_matrix = Mat4::orthographic_rh(0.0, view_width, 0.0, view_height, 0.0, 100.0);
-        //proj_matrix.inverse();
-
-        Self {
-            proj_matrix,
+        let mut camera = Self {
+            proj_matrix: Mat4::ZERO,
             view_matrix: Mat4:: ZERO,
 
             inverse_proj: Mat4::ZERO,
@@ -32,17 +34,29 @@ impl Camera {
             view_width,
             view_height,
 
+            zoom: 1f32,
+
             position: Vec2::new(x, y)
-        }
+        };
+
+        camera.update_projection();
+        camera.update_view();
+
+        camera
     }
 
     pub fn adjust_projection(&mut self, view_width: f32, view_height: f32) {
         self.view_width = view_width;
         self.view_height = view_height;
-        self.proj_matrix = Mat4::orthographic_rh(0.0, view_width, 0.0, view_height, 0.0, 100.0);
+        self.update_projection();
     }
 
-    pub fn get_view_matrix(&mut self) -> Mat4 {
+    fn update_projection(&mut self) {
+        self.proj_matrix = Mat4::orthographic_rh(0.0, self.view_width / self.zoom, 0.0, self.view_height / self.zoom, 0.0, 100.0);
+        self.inverse_proj = self.proj_matrix.inverse();
+    }
+
+    fn update_view(&mut self) {
         let cam_front = Vec3::new(0.0, 0.0, -1.0);
         let cam_up = Vec3::new(0.0, 1.0, 0.0);
 
@@ -51,9 +65,64 @@ impl Camera {
             cam_front.add(Vec3::new(self.position.x, self.position.y, 0f32)),
             cam_up
         );
+        self.inverse_view = self.view_matrix.inverse();
+    }
+
+    /// Converts a window position in pixels, with y pointing down, into world coordinates
+    pub fn screen_to_world(&self, screen: Vec2) -> Vec2 {
+        let ndc = Vec4::new(screen.x / self.view_width * 2f32 - 1f32, 1f32 - screen.y / self.view_height * 2f32, 0f32, 1f32);
+        let world = self.inverse_view * self.inverse_proj * ndc;
+
+        Vec2::new(world.x, world.y)
+    }
+
+    pub fn world_to_screen(&self, world: Vec2) -> Vec2 {
+        let ndc = self.proj_matrix * self.view_matrix * Vec4::new(world.x, world.y, 0f32, 1f32);
+
+        Vec2::new((ndc.x + 1f32) / 2f32 * self.view_width, (1f32 - ndc.y) / 2f32 * self.view_height)
+    }
+
+    /// Zooms and centers the view so the world rectangle fits inside the screen rectangle without distortion
+    pub fn fit(&mut self, world_min: Vec2, world_size: Vec2, screen_min: Vec2, screen_size: Vec2) {
+        self.zoom = (screen_size.x / world_size.x).min(screen_size.y / world_size.y).max(f32::EPSILON);
+        self.update_projection();
+
+        let screen_center = screen_min + screen_size / 2f32;
+        self.position = world_min + world_size / 2f32 - Vec2::new(screen_center.x, self.view_height - screen_center.y) / self.zoom;
+        self.update_view();
+    }
+
+    /// Scales the zoom by `factor` while keeping the world point under the screen position in place
+    pub fn zoom_at(&mut self, screen: Vec2, factor: f32) {
+        let anchor = self.screen_to_world(screen);
+
+        self.zoom = (self.zoom * factor).clamp(MIN_ZOOM, MAX_ZOOM);
+        self.update_projection();
+
+        self.position += anchor - self.screen_to_world(screen);
+        self.update_view();
+    }
+
+    /// Moves the view by a mouse delta in screen pixels, so the world follows the cursor
+    pub fn pan(&mut self, screen_delta: Vec2) {
+        self.position -= Vec2::new(screen_delta.x, -screen_delta.y) / self.zoom;
+        self.update_view();
+    }
+
+    pub fn set_position(&mut self, position: Vec2) {
+        self.position = position;
+        self.update_view();
+    }
+
+    pub fn get_position(&self) -> Vec2 {
+        self.position
+    }
 
-        //self.view_matrix.inverse();
+    pub fn get_zoom(&self) -> f32 {
+        self.zoom
+    }
 
+    pub fn get_view_matrix(&self) -> Mat4 {
         self.view_matrix
     }
 
@@ -68,4 +137,76 @@ impl Camera {
     pub fn get_view_height(&self) -> f32 {
         self.view_height
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: Vec2, b: Vec2) {
+        assert!((a - b).abs().max_element() < 1e-2, "{} != {}", a, b);
+    }
+
+    /// Cameras over an 800x600 window at several zoom levels and positions
+    fn cameras() -> Vec<Camera> {
+        let mut cameras = Vec::new();
+
+        for zoom in [MIN_ZOOM, 0.5, 1f32, 3.5, MAX_ZOOM] {
+            for position in [Vec2::ZERO, Vec2::new(120f32, -45f32), Vec2::new(-300.5, 1000f32)] {
+                let mut camera = Camera::new(0f32, 0f32, 800f32, 600f32);
+                camera.zoom_at(Vec2::ZERO, zoom);
+                camera.set_position(position);
+
+                cameras.push(camera);
+            }
+        }
+
+        cameras
+    }
+
+    #[test]
+    fn screen_and_world_round_trip() {
+        for camera in cameras() {
+            for screen in [Vec2::ZERO, Vec2::new(800f32, 600f32), Vec2::new(13f32, 577f32), Vec2::new(400f32, 300f32)] {
+                assert_close(camera.world_to_screen(camera.screen_to_world(screen)), screen);
+            }
+
+            for world in [Vec2::ZERO, Vec2::new(55.5, 210f32), Vec2::new(-80f32, -12f32)] {
+                assert_close(camera.screen_to_world(camera.world_to_screen(world)), world);
+            }
+        }
+    }
+
+    #[test]
+    fn bottom_left_of_the_window_is_the_camera_position() {
+        for camera in cameras() {
+            let position = camera.get_position();
+
+            assert_close(camera.screen_to_world(Vec2::new(0f32, 600f32)), position);
+            assert_close(camera.screen_to_world(Vec2::new(800f32, 0f32)), position + Vec2::new(800f32, 600f32) / camera.get_zoom());
+        }
+    }
+
+    #[test]
+    fn zoom_at_keeps_the_anchor_fixed_on_screen() {
+        for mut camera in cameras() {
+            for (screen, factor) in [(Vec2::new(200f32, 150f32), 2f32), (Vec2::new(790f32, 10f32), 0.5), (Vec2::new(400f32, 300f32), 1.25)] {
+                let anchor = camera.screen_to_world(screen);
+                camera.zoom_at(screen, factor);
+
+                assert_close(camera.world_to_screen(anchor), screen);
+            }
+        }
+    }
+
+    #[test]
+    fn zoom_is_clamped() {
+        let mut camera = Camera::new(0f32, 0f32, 800f32, 600f32);
+
+        camera.zoom_at(Vec2::ZERO, 1000f32);
+        assert_eq!(camera.get_zoom(), MAX_ZOOM);
+
+        camera.zoom_at(Vec2::ZERO, 0.0001);
+        assert_eq!(camera.get_zoom(), MIN_ZOOM);
+    }
+}
@@ -1,22 +1,21 @@
 #![allow(dead_code)]
 
-use std::{collections::HashSet, time::Instant};
+use std::{cell::RefCell, collections::HashSet, rc::Rc, time::Instant};
 
 use miniquad::*;
 
-use crate::Game;
+use super::{error::EngineError, triangle};
 
-use super::triangle;
-
-pub struct Engine {
-    game_logic: Game,
+pub struct Engine<G: GameLogic> {
+    game_logic: G,
 
     clear_color: (f32, f32, f32, f32),
 
-    pipeline: Pipeline,
-    bindings: Bindings,
+    triangle: Option<(Pipeline, Bindings)>,
 
     initialized: bool,
+    /// Set when something failed, the engine then only shows the error
+    error: Rc<RefCell<Option<EngineError>>>,
 
     egui_mq: egui_miniquad::EguiMq,
 
@@ -35,42 +34,125 @@ pub struct Input {
     pub mouse_dx: f32,
     pub mouse_dy: f32,
 
+    /// Wheel movement accumulated since the last update
+    pub wheel_dx: f32,
+    pub wheel_dy: f32,
+
     pub last_char: char,
+
     keys_down: HashSet<KeyCode>,
+    keys_pressed: HashSet<KeyCode>,
+    keys_released: HashSet<KeyCode>,
 
-    buttons_down: HashSet<MouseButton>
+    buttons_down: HashSet<MouseButton>,
+    buttons_pressed: HashSet<MouseButton>,
+    buttons_released: HashSet<MouseButton>
 }
 
 impl Input {
+    fn new() -> Self {
+        Self {
+            mouse_x: 0f32,
+            mouse_y: 0f32,
+            mouse_dx: 0f32,
+            mouse_dy: 0f32,
+
+            wheel_dx: 0f32,
+            wheel_dy: 0f32,
+
+            last_char: ' ',
+
+            keys_down: HashSet::new(),
+            keys_pressed: HashSet::new(),
+            keys_released: HashSet::new(),
+
+            buttons_down: HashSet::new(),
+            buttons_pressed: HashSet::new(),
+            buttons_released: HashSet::new()
+        }
+    }
+
     pub fn is_button_down(&self, button: MouseButton) -> bool {
         self.buttons_down.contains(&button)
     }
 
+    /// True only during the update after the button went down
+    pub fn is_button_pressed(&self, button: MouseButton) -> bool {
+        self.buttons_pressed.contains(&button)
+    }
+
+    pub fn is_button_released(&self, button: MouseButton) -> bool {
+        self.buttons_released.contains(&button)
+    }
+
     pub fn is_key_down(&self, key: KeyCode) -> bool {
         self.keys_down.contains(&key)
     }
+
+    /// True only during the update after the key went down, key repeats are ignored
+    pub fn is_key_pressed(&self, key: KeyCode) -> bool {
+        self.keys_pressed.contains(&key)
+    }
+
+    pub fn is_key_released(&self, key: KeyCode) -> bool {
+        self.keys_released.contains(&key)
+    }
+
+    /// Modifier keys held right now, read from the held keys since the modifiers sent with key events lag behind on X11
+    pub fn modifiers(&self) -> KeyMods {
+        let either = |left, right| self.is_key_down(left) || self.is_key_down(right);
+
+        KeyMods {
+            shift: either(KeyCode::LeftShift, KeyCode::RightShift),
+            ctrl: either(KeyCode::LeftControl, KeyCode::RightControl),
+            alt: either(KeyCode::LeftAlt, KeyCode::RightAlt),
+            logo: either(KeyCode::LeftSuper, KeyCode::RightSuper)
+        }
+    }
+
+    /// Clears everything that only lasts a single update
+    fn end_frame(&mut self) {
+        self.wheel_dx = 0f32;
+        self.wheel_dy = 0f32;
+
+        self.keys_pressed.clear();
+        self.keys_released.clear();
+
+        self.buttons_pressed.clear();
+        self.buttons_released.clear();
+    }
 }
 
 pub struct Info {
     pub perf: Perf,
-    pub input: Input
+    pub input: Input,
+    /// egui has keyboard focus, e.g. a text field is being edited, so key presses belong to the UI
+    pub ui_wants_keyboard: bool,
+    /// The pointer is over a window or dragging a widget, so clicks and the wheel belong to the UI
+    pub ui_wants_pointer: bool
 }
 
 
-impl Engine {
-    pub fn new(game_logic: Game, ctx: &mut miniquad::Context) -> Self {
+impl<G: GameLogic> Engine<G> {
+    pub fn new(game_logic: G, ctx: &mut miniquad::Context, error: Rc<RefCell<Option<EngineError>>>) -> Self {
 
-        let triangle_info = triangle::create_triangle(ctx);
+        let triangle = match triangle::create_triangle(ctx) {
+            Ok(triangle) => Some(triangle),
+            Err(err) => {
+                *error.borrow_mut() = Some(err);
+                None
+            }
+        };
 
         Self {
             game_logic,
             
             clear_color: (0f32, 0f32, 0f32, 0f32),
 
-            pipeline: triangle_info.0,
-            bindings: triangle_info.1,
+            triangle,
 
             initialized: false,
+            error,
 
             egui_mq: egui_miniquad::EguiMq::new(ctx),
 
@@ -81,27 +163,27 @@ impl Engine {
                     fps: 60f32,
                 },
 
-                input: Input {
-                    mouse_x: 0f32,
-                    mouse_y: 0f32,
-                    mouse_dx: 0f32,
-                    mouse_dy: 0f32,
-
-                    last_char: ' ',
-                    keys_down: HashSet::new(),
-
-                    buttons_down: HashSet::new()
-                }
+                input: Input::new(),
+                ui_wants_keyboard: false,
+                ui_wants_pointer: false
             }
         }
     }
 }
 
-impl EventHandler for Engine {
+impl<G: GameLogic> EventHandler for Engine<G> {
     fn update(&mut self, ctx: &mut Context) {
+        if self.error.borrow().is_some() {
+            return;
+        }
+
         if !self.initialized {
-            self.game_logic.init(ctx);
             self.initialized = true;
+
+            if let Err(err) = self.game_logic.init(ctx) {
+                *self.error.borrow_mut() = Some(err);
+                return;
+            }
         }
 
         let now = std::time::Instant::now();
@@ -112,6 +194,8 @@ impl EventHandler for Engine {
         self.info.perf.last_frame = now;
 
         self.game_logic.update(&self.info);
+
+        self.info.input.end_frame();
     }
 
     fn draw(&mut self, ctx: &mut Context) {
@@ -123,11 +207,23 @@ impl EventHandler for Engine {
 
         // ctx.draw(0, 3, 1);
 
-        self.game_logic.render(ctx, &mut self.clear_color);
+        if let Some(err) = self.error.borrow().as_ref() {
+            self.egui_mq.run(ctx, |egui_ctx| failure_screen(err, egui_ctx));
+        } else if self.initialized {
+            self.game_logic.render(ctx, &mut self.clear_color);
 
-        self.egui_mq.run(ctx, |egui_ctx| {
-            self.game_logic.render_egui(&self.info, egui_ctx);
-        });
+            let (mut wants_keyboard, mut wants_pointer) = (false, false);
+
+            self.egui_mq.run(ctx, |egui_ctx| {
+                self.game_logic.render_egui(&self.info, egui_ctx);
+
+                wants_keyboard = egui_ctx.wants_keyboard_input();
+                wants_pointer = egui_ctx.wants_pointer_input();
+            });
+
+            self.info.ui_wants_keyboard = wants_keyboard;
+            self.info.ui_wants_pointer = wants_pointer;
+        }
 
         ctx.end_render_pass();
 
@@ -143,6 +239,9 @@ impl EventHandler for Engine {
     }
 
     fn mouse_wheel_event(&mut self, ctx: &mut miniquad::Context, dx: f32, dy: f32) {
+        self.info.input.wheel_dx += dx;
+        self.info.input.wheel_dy += dy;
+
         self.egui_mq.mouse_wheel_event(ctx, dx, dy);
     }
 
@@ -153,11 +252,13 @@ impl EventHandler for Engine {
 
     fn mouse_button_down_event(&mut self, ctx: &mut Context, button: MouseButton, x: f32, y: f32) {
         self.info.input.buttons_down.insert(button);
+        self.info.input.buttons_pressed.insert(button);
         self.egui_mq.mouse_button_down_event(ctx, button, x, y);
     }
 
     fn mouse_button_up_event(&mut self, ctx: &mut Context, button: MouseButton, x: f32, y: f32) {
         self.info.input.buttons_down.remove(&button);
+        self.info.input.buttons_released.insert(button);
         self.egui_mq.mouse_button_up_event(ctx, button, x, y);
     }
 
@@ -166,19 +267,24 @@ impl EventHandler for Engine {
         self.info.input.mouse_dy = dy;
     }
 
-    fn key_down_event(&mut self, ctx: &mut Context, keycode: KeyCode, keymods: KeyMods, _: bool) {
+    fn key_down_event(&mut self, ctx: &mut Context, keycode: KeyCode, keymods: KeyMods, repeat: bool) {
         self.info.input.keys_down.insert(keycode);
 
+        if !repeat {
+            self.info.input.keys_pressed.insert(keycode);
+        }
+
         self.egui_mq.key_down_event(ctx, keycode, keymods);
     }
 
     fn key_up_event(&mut self, _: &mut Context, keycode: KeyCode, keymods: KeyMods) {
         self.info.input.keys_down.remove(&keycode);
+        self.info.input.keys_released.insert(keycode);
         self.egui_mq.key_up_event(keycode, keymods);
     }
 
-    fn resize_event(&mut self, _ctx: &mut Context, _width: f32, _height: f32) {
-
+    fn resize_event(&mut self, _ctx: &mut Context, width: f32, height: f32) {
+        self.game_logic.resize(width, height);
     }
 
     fn touch_event(&mut self, ctx: &mut Context, phase: TouchPhase, _id: u64, x: f32, y: f32) {
@@ -196,32 +302,56 @@ impl EventHandler for Engine {
     }
 }
 
-pub fn start_engine(title: &str, width: i32, height: i32, fullscreen: bool, game_logic: Game) {
+/// Runs the game until the window is closed, returning the error that stopped it if there was one
+pub fn start_engine<G: GameLogic + 'static>(title: &str, width: i32, height: i32, fullscreen: bool, game_logic: G) -> Result<(), EngineError> {
+    if width <= 0 || height <= 0 {
+        return Err(EngineError::Init(format!("invalid window size {}x{}", width, height)));
+    }
+
+    let error = Rc::new(RefCell::new(None));
+    let engine_error = error.clone();
+
     miniquad::start(
         conf::Conf {
             window_title: title.to_string(),
             window_width: width,
             window_height: height,
             fullscreen,
-            window_resizable: false,
+            window_resizable: true,
             sample_count:0,
             high_dpi: false,
             
             ..Default::default()
         }, 
-        move |mut ctx| UserData::owning(Engine::new(game_logic, &mut ctx), ctx),
+        move |mut ctx| UserData::owning(Engine::new(game_logic, &mut ctx, engine_error), ctx),
     );
+
+    match error.take() {
+        Some(err) => Err(err),
+        None => Ok(())
+    }
 }
 
-pub trait GameLogic {
-    fn new() -> Self;
+fn failure_screen(err: &EngineError, egui_ctx: &egui::Context) {
+    egui::CentralPanel::default().show(egui_ctx, |ui| {
+        ui.heading("Something went wrong");
+        ui.add_space(10f32);
 
-    fn init(&mut self, ctx: &mut Context);
+        ui.label(egui::RichText::new(err.to_string()).monospace());
 
-    fn update(&mut self, input: &Input);
+        ui.add_space(10f32);
+        ui.label("Close the window to exit.");
+    });
+}
+
+pub trait GameLogic {
+    fn init(&mut self, ctx: &mut Context) -> Result<(), EngineError>;
+
+    fn update(&mut self, info: &Info);
     
     fn render(&mut self, ctx: &mut Context, bg_color: &mut (f32, f32, f32, f32));
 
-    fn render_egui(&mut self, egui_ctx: &egui::Context);
-}
+    fn render_egui(&mut self, info: &Info, egui_ctx: &egui::Context);
 
+    fn resize(&mut self, _width: f32, _height: f32) {}
+}
@@ -0,0 +1,35 @@
+use std::{fmt, io};
+
+use miniquad::ShaderError;
+
+use super::shader;
+
+#[derive(Debug)]
+pub enum EngineError {
+    /// A shader failed to compile or link, `name` says which one
+    Shader { name: &'static str, source: ShaderError },
+    /// A file the engine needs could not be read
+    Resource { path: String, source: io::Error },
+    /// The game failed to set itself up
+    Init(String)
+}
+
+impl fmt::Display for EngineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EngineError::Shader { name, source } => write!(f, "{} shader: {}", name, shader::describe_error(source)),
+            EngineError::Resource { path, source } => write!(f, "failed to read {}: {}", path, source),
+            EngineError::Init(message) => write!(f, "failed to initialize: {}", message)
+        }
+    }
+}
+
+impl std::error::Error for EngineError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            EngineError::Shader { source, .. } => Some(source),
+            EngineError::Resource { source, .. } => Some(source),
+            EngineError::Init(_) => None
+        }
+    }
+}
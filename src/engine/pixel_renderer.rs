@@ -0,0 +1,180 @@
+#![allow(dead_code)]
+
+use glam::Vec2;
+use miniquad::{Shader, Bindings, Pipeline, PipelineParams, Context, Buffer, BufferLayout, VertexAttribute, VertexFormat, BufferType,
+    Texture, TextureParams, TextureFormat, TextureWrap, FilterMode};
+
+use super::{shader, camera::Camera, error::EngineError, shape_renderer::BlendMode};
+
+/// RGBA8 pixels kept on the CPU, row 0 is the bottom of the image
+pub struct PixelBuffer {
+    width: usize,
+    height: usize,
+    pixels: Vec<u8>
+}
+
+impl PixelBuffer {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self { width, height, pixels: vec![0; width * height * 4] }
+    }
+
+    pub fn set_pixel(&mut self, x: usize, y: usize, color: (u8, u8, u8, u8)) {
+        let index = (y * self.width + x) * 4;
+        self.pixels[index..index + 4].copy_from_slice(&[color.0, color.1, color.2, color.3]);
+    }
+
+    pub fn get_pixel(&self, x: usize, y: usize) -> (u8, u8, u8, u8) {
+        let index = (y * self.width + x) * 4;
+        (self.pixels[index], self.pixels[index + 1], self.pixels[index + 2], self.pixels[index + 3])
+    }
+
+    pub fn clear(&mut self) {
+        self.pixels.fill(0);
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        &self.pixels
+    }
+}
+
+#[repr(C)]
+struct Vertex {
+    pos: Vec2,
+    uv: Vec2
+}
+
+/// Streams a `PixelBuffer` into a texture and draws it on a single quad, one texel per grid cell
+pub struct PixelRenderer {
+    buffer: PixelBuffer,
+    texture: Texture,
+
+    bindings: Bindings,
+    pipeline: Pipeline,
+    blend_mode: BlendMode
+}
+
+impl PixelRenderer {
+    pub fn new(ctx: &mut Context, width: usize, height: usize) -> Result<Self, EngineError> {
+        let shader = Shader::new(ctx, shader::texture_vertex_shader(), shader::texture_fragment_shader(), shader::texture_meta())
+            .map_err(|source| EngineError::Shader { name: "texture", source })?;
+
+        let buffer = PixelBuffer::new(width, height);
+
+        let texture = Texture::from_data_and_format(ctx, buffer.bytes(), TextureParams {
+            format: TextureFormat::RGBA8,
+            wrap: TextureWrap::Clamp,
+            filter: FilterMode::Nearest,
+            width: width as u32,
+            height: height as u32
+        });
+
+        let vertex_buffer = Buffer::stream(ctx, BufferType::VertexBuffer, 4 * std::mem::size_of::<Vertex>());
+
+        let indices: [u16; 6] = [0, 1, 2, 2, 0, 3];
+        let index_buffer = Buffer::immutable(ctx, BufferType::IndexBuffer, &indices);
+
+        let bindings = Bindings {
+            vertex_buffers: vec![vertex_buffer],
+            index_buffer,
+            images: vec![texture],
+        };
+
+        let pipeline = Pipeline::with_params(
+            ctx,
+            &[BufferLayout::default()],
+            &[VertexAttribute::with_buffer("pos", VertexFormat::Float2, 0),
+            VertexAttribute::with_buffer("uv", VertexFormat::Float2, 0)],
+            shader,
+            PipelineParams {
+                color_blend: BlendMode::Alpha.blend_state(),
+                ..Default::default()
+            }
+        );
+
+        Ok(Self { buffer, texture, bindings, pipeline, blend_mode: BlendMode::Alpha })
+    }
+
+    pub fn set_blend_mode(&mut self, ctx: &mut Context, blend_mode: BlendMode) {
+        self.pipeline.set_blend(ctx, blend_mode.blend_state());
+        self.blend_mode = blend_mode;
+    }
+
+    pub fn get_blend_mode(&self) -> BlendMode {
+        self.blend_mode
+    }
+
+    /// The CPU side pixels, written to before calling `draw`
+    pub fn buffer(&mut self) -> &mut PixelBuffer {
+        &mut self.buffer
+    }
+
+    /// Uploads the pixels and draws them stretched over the world rectangle
+    pub fn draw(&mut self, ctx: &mut Context, camera: &mut Camera, x: f32, y: f32, width: f32, height: f32) {
+        self.texture.update(ctx, self.buffer.bytes());
+
+        let vertices = [
+            Vertex { pos: Vec2::new(x, y), uv: Vec2::new(0f32, 0f32) },
+            Vertex { pos: Vec2::new(x + width, y), uv: Vec2::new(1f32, 0f32) },
+            Vertex { pos: Vec2::new(x + width, y + height), uv: Vec2::new(1f32, 1f32) },
+            Vertex { pos: Vec2::new(x, y + height), uv: Vec2::new(0f32, 1f32) }
+        ];
+        self.bindings.vertex_buffers[0].update(ctx, &vertices);
+
+        ctx.apply_pipeline(&self.pipeline);
+        ctx.apply_bindings(&self.bindings);
+        ctx.apply_uniforms(&shader::Uniforms { uProjection: camera.get_proj_matrix(), uView: camera.get_view_matrix()});
+
+        ctx.draw(0, 6, 1);
+    }
+
+    pub fn num_of_texels(&self) -> usize {
+        self.buffer.width() * self.buffer.height()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new_buffer_is_transparent_black() {
+        let buffer = PixelBuffer::new(3, 2);
+
+        assert_eq!((buffer.width(), buffer.height()), (3, 2));
+        assert_eq!(buffer.bytes().len(), 3 * 2 * 4);
+        assert!(buffer.bytes().iter().all(|&byte| byte == 0));
+    }
+
+    #[test]
+    fn pixels_are_stored_row_by_row() {
+        let mut buffer = PixelBuffer::new(3, 2);
+        buffer.set_pixel(2, 1, (1, 2, 3, 4));
+        buffer.set_pixel(0, 1, (5, 6, 7, 8));
+
+        assert_eq!(buffer.get_pixel(2, 1), (1, 2, 3, 4));
+        assert_eq!(buffer.get_pixel(0, 1), (5, 6, 7, 8));
+        assert_eq!(buffer.get_pixel(1, 1), (0, 0, 0, 0));
+        assert_eq!(&buffer.bytes()[20..24], &[1, 2, 3, 4]);
+        assert_eq!(&buffer.bytes()[12..16], &[5, 6, 7, 8]);
+    }
+
+    #[test]
+    fn set_pixel_overwrites_and_clear_resets() {
+        let mut buffer = PixelBuffer::new(2, 2);
+        buffer.set_pixel(1, 0, (9, 9, 9, 9));
+        buffer.set_pixel(1, 0, (1, 1, 1, 1));
+
+        assert_eq!(buffer.get_pixel(1, 0), (1, 1, 1, 1));
+
+        buffer.clear();
+        assert!(buffer.bytes().iter().all(|&byte| byte == 0));
+    }
+}
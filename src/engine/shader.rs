@@ -1,17 +1,80 @@
 #![allow(dead_code)]
 
+use std::{fs, path::{Path, PathBuf}, time::SystemTime};
+
 use miniquad::*;
 
-pub fn vertex_shader() -> String {
-    let vertex_shader_src = std::fs::read_to_string("src/engine/vertex.glsl").unwrap();
+use super::error::EngineError;
+
+pub fn vertex_shader() -> &'static str {
+    include_str!("vertex.glsl")
+}
+
+pub fn fragment_shader() -> &'static str {
+    include_str!("fragment.glsl")
+}
+
+pub fn texture_vertex_shader() -> &'static str {
+    include_str!("texture_vertex.glsl")
+}
+
+pub fn texture_fragment_shader() -> &'static str {
+    include_str!("texture_fragment.glsl")
+}
+
+/// Shader sources in the source tree, read by the dev mode instead of the embedded copies
+pub const VERTEX_SHADER_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/src/engine/vertex.glsl");
+pub const FRAGMENT_SHADER_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/src/engine/fragment.glsl");
+
+/// Reads the shape shaders from the source tree for the dev mode
+pub fn read_shader_sources() -> Result<(String, String), EngineError> {
+    let read = |path: &str| fs::read_to_string(path).map_err(|source| EngineError::Resource { path: path.to_string(), source });
+
+    Ok((read(VERTEX_SHADER_PATH)?, read(FRAGMENT_SHADER_PATH)?))
+}
+
+/// Polls files for modification, used to hot-reload shaders while developing
+pub struct ShaderWatcher {
+    paths: Vec<PathBuf>,
+    modified: Vec<Option<SystemTime>>
+}
+
+impl ShaderWatcher {
+    pub fn new(paths: &[&str]) -> Self {
+        let paths: Vec<PathBuf> = paths.iter().map(PathBuf::from).collect();
+        let modified = paths.iter().map(|path| modified(path)).collect();
 
-    vertex_shader_src
+        Self { paths, modified }
+    }
+
+    /// Returns true if any of the files changed since the last call
+    pub fn poll(&mut self) -> bool {
+        let mut changed = false;
+
+        for (path, last) in self.paths.iter().zip(self.modified.iter_mut()) {
+            let current = modified(path);
+
+            if current != *last {
+                *last = current;
+                changed = true;
+            }
+        }
+
+        changed
+    }
 }
 
-pub fn fragment_shader() -> String {
-    let fragment_shader_src = std::fs::read_to_string("src/engine/fragment.glsl").unwrap();
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
 
-    fragment_shader_src
+/// Compile and link errors with the driver log on its own lines, instead of the escaped debug output
+pub fn describe_error(err: &ShaderError) -> String {
+    match err {
+        ShaderError::CompilationError { shader_type, error_message } => format!("{:?} shader failed to compile:\n{}", shader_type, error_message.trim_end_matches('\0').trim()),
+        ShaderError::LinkError(message) => format!("shader failed to link:\n{}", message.trim_end_matches('\0').trim()),
+        ShaderError::FFINulError(err) => format!("shader source contains a nul byte: {}", err)
+    }
 }
 
 pub fn meta() -> ShaderMeta {
@@ -24,7 +87,18 @@ pub fn meta() -> ShaderMeta {
     }
 }
 
+pub fn texture_meta() -> ShaderMeta {
+    ShaderMeta {
+        images: vec![String::from("tex")],
+        uniforms: UniformBlockLayout {
+            uniforms: vec![UniformDesc::new("uProjection", UniformType::Mat4),
+                            UniformDesc::new("uView", UniformType::Mat4)]
+        },
+    }
+}
+
 #[repr(C)]
+#[allow(non_snake_case)]
 pub struct Uniforms {
     pub uProjection: glam::Mat4,
     pub uView: glam::Mat4,
@@ -1,11 +1,43 @@
 #![allow(dead_code)]
 
 use glam::{Vec2};
-use miniquad::{Shader, Bindings, Pipeline, Context, Buffer, BufferLayout, VertexAttribute, VertexFormat, BufferType, BlendState, BlendFactor, Equation, BlendValue};
+use miniquad::{Shader, Bindings, Pipeline, Context, Buffer, BufferLayout, VertexAttribute, VertexFormat, BufferType, IndexType, BlendState, Equation, BlendFactor, BlendValue};
 
-use super::{shader, camera::Camera};
+use super::{shader, camera::Camera, error::EngineError};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BlendMode {
+    Opaque, Alpha, Additive, Multiply
+}
+
+impl BlendMode {
+    pub const ALL: [BlendMode; 4] = [BlendMode::Opaque, BlendMode::Alpha, BlendMode::Additive, BlendMode::Multiply];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            BlendMode::Opaque => "opaque",
+            BlendMode::Alpha => "alpha",
+            BlendMode::Additive => "additive",
+            BlendMode::Multiply => "multiply"
+        }
+    }
+
+    /// Color blend state for the pipeline, `None` overwrites the destination
+    pub fn blend_state(&self) -> Option<BlendState> {
+        match self {
+            BlendMode::Opaque => None,
+            BlendMode::Alpha => Some(BlendState::new(Equation::Add, BlendFactor::Value(BlendValue::SourceAlpha), BlendFactor::OneMinusValue(BlendValue::SourceAlpha))),
+            BlendMode::Additive => Some(BlendState::new(Equation::Add, BlendFactor::Value(BlendValue::SourceAlpha), BlendFactor::One)),
+            BlendMode::Multiply => Some(BlendState::new(Equation::Add, BlendFactor::Value(BlendValue::DestinationColor), BlendFactor::Zero))
+        }
+    }
+}
+
+/// Upper bound of indices per vertex over all shapes, so the index buffer never overflows
+const INDICES_PER_VERTEX: usize = 3;
 
 #[derive(Copy, Clone)]
+#[repr(C)]
 struct Color {
     r: f32,
     g: f32,
@@ -13,53 +45,28 @@ struct Color {
     a: f32
 }
 
+#[repr(C)]
 struct Vertex {
     pos: Vec2,
     color: Color,
 }
 
+/// Vertices and indices of up to `max_batch_size` vertices, drawn with a single draw call
 pub struct ShapeBatch {
     vertices: Vec<Vertex>,
     indices: Vec<i32>,
     color: Color,
 
-    bindings: Bindings,
-    pipeline: Pipeline,
     max_batch_size: usize
 }
 
 impl ShapeBatch {
-    pub fn new(ctx: &mut Context, max_batch_size: usize) -> Self {
-        let shader = Shader::new(ctx, &shader::vertex_shader(), &shader::fragment_shader(), shader::meta()).unwrap();
-
-        let vertices: Vec<Vertex> = Vec::new();
-        let indices: Vec<i32> = Vec::new();
-
-        let vertex_buffer = Buffer::stream(ctx, BufferType::VertexBuffer, std::mem::size_of::<f32>() * max_batch_size * std::mem::size_of::<Vertex>());
-
-        let index_buffer = Buffer::stream(ctx, BufferType::IndexBuffer, max_batch_size * std::mem::size_of::<f32>());
-
-        let bindings = Bindings {
-            vertex_buffers: vec![vertex_buffer],
-            index_buffer,
-            images: vec![],
-        };
-    
-        let pipeline = Pipeline::new(
-            ctx, 
-            &[BufferLayout::default()], 
-            &[VertexAttribute::with_buffer("pos", VertexFormat::Float2, 0),
-            VertexAttribute::with_buffer("color", VertexFormat::Float4, 0)], 
-            shader
-        );
-
-        Self { 
-            vertices, 
-            indices,
+    pub fn new(max_batch_size: usize) -> Self {
+        Self {
+            vertices: Vec::with_capacity(max_batch_size),
+            indices: Vec::with_capacity(max_batch_size * INDICES_PER_VERTEX),
             color: Color { r: 1.0, g: 1.0, b: 1.0, a: 1.0 },
 
-            bindings,
-            pipeline,
             max_batch_size
         }
     }
@@ -69,6 +76,15 @@ impl ShapeBatch {
         self.indices.clear();
     }
 
+    /// Whether a shape with this many vertices still fits
+    pub fn has_room(&self, vertices: usize) -> bool {
+        self.vertices.len() + vertices <= self.max_batch_size
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+
     pub fn set_color(&mut self, r: f32, g: f32, b: f32, a: f32) {
         self.color = Color {r, g, b , a};
     }
@@ -100,31 +116,91 @@ impl ShapeBatch {
         self.indices.push(i - 1);
     }
 
-    pub fn end(&mut self, ctx: &mut Context, camera: &mut Camera) {
-        self.bindings.vertex_buffers[0].update(ctx, &self.vertices);
+    /// A quad of `thickness` centered on the segment
+    pub fn draw_line(&mut self, x0: f32, y0: f32, x1: f32, y1: f32, thickness: f32) {
+        let direction = Vec2::new(x1 - x0, y1 - y0).normalize_or_zero();
+        let offset = Vec2::new(-direction.y, direction.x) * thickness / 2f32;
+
+        self.draw_quad([
+            Vec2::new(x0, y0) + offset, Vec2::new(x0, y0) - offset,
+            Vec2::new(x1, y1) - offset, Vec2::new(x1, y1) + offset
+        ]);
+    }
+
+    /// A triangle fan around the center
+    pub fn draw_circle(&mut self, x: f32, y: f32, radius: f32, segments: usize) {
+        let segments = segments.max(3);
+
+        self.vertices.push(Vertex { pos: Vec2 { x, y }, color: self.color });
+        let center = self.vertices.len() as i32 - 1;
+
+        for segment in 0..segments {
+            self.vertices.push(Vertex { pos: Vec2::new(x, y) + circle_point(segment, segments) * radius, color: self.color });
+        }
+
+        for segment in 0..segments as i32 {
+            self.indices.push(center);
+            self.indices.push(center + 1 + segment);
+            self.indices.push(center + 1 + (segment + 1) % segments as i32);
+        }
+    }
+
+    /// A circle outline `thickness` wide, lying inside `radius`
+    pub fn draw_ring(&mut self, x: f32, y: f32, radius: f32, thickness: f32, segments: usize) {
+        let segments = segments.max(3);
+        let inner_radius = (radius - thickness).max(0f32);
+
+        let first = self.vertices.len() as i32;
+        for segment in 0..segments {
+            let point = circle_point(segment, segments);
+
+            self.vertices.push(Vertex { pos: Vec2::new(x, y) + point * radius, color: self.color });
+            self.vertices.push(Vertex { pos: Vec2::new(x, y) + point * inner_radius, color: self.color });
+        }
+
+        for segment in 0..segments as i32 {
+            let outer = first + segment * 2;
+            let next = first + (segment + 1) % segments as i32 * 2;
+
+            self.indices.extend_from_slice(&[outer, next, outer + 1, outer + 1, next, next + 1]);
+        }
+    }
+
+    /// A convex polygon, triangulated as a fan from the first point
+    pub fn draw_polygon(&mut self, points: &[Vec2]) {
+        if points.len() < 3 {
+            return;
+        }
 
-        self.bindings.index_buffer.delete();
-        self.bindings.index_buffer = Buffer::immutable(ctx, BufferType::IndexBuffer, &self.indices);
+        let first = self.vertices.len() as i32;
+        for point in points {
+            self.vertices.push(Vertex { pos: *point, color: self.color });
+        }
 
-        ctx.apply_pipeline(&self.pipeline);
+        for i in 1..points.len() as i32 - 1 {
+            self.indices.push(first);
+            self.indices.push(first + i);
+            self.indices.push(first + i + 1);
+        }
+    }
 
-        // let color_blend = BlendState::new(
-        //     Equation::Add,
-        //     BlendFactor::Value(BlendValue::SourceColor),
-        //     BlendFactor::OneMinusValue(BlendValue::SourceColor),
-        // );
-        // let alpha_blend = BlendState::new(
-        //     Equation::Add,
-        //     BlendFactor::Value(BlendValue::SourceAlpha),
-        //     BlendFactor::OneMinusValue(BlendValue::SourceAlpha),
-        // );
-        // ctx.set_blend(Some(color_blend), Some(alpha_blend));
-        
-        ctx.apply_bindings(&self.bindings);
-        ctx.apply_uniforms(&shader::Uniforms { uProjection: camera.get_proj_matrix(), uView: camera.get_view_matrix()});
+    /// The border of a rectangle, `thickness` wide and lying inside it
+    pub fn draw_rect_outline(&mut self, x: f32, y: f32, width: f32, height: f32, thickness: f32) {
+        let thickness = thickness.min(width / 2f32).min(height / 2f32);
+
+        self.draw_rect(x, y, width, thickness);
+        self.draw_rect(x, y + height - thickness, width, thickness);
+        self.draw_rect(x, y + thickness, thickness, height - thickness * 2f32);
+        self.draw_rect(x + width - thickness, y + thickness, thickness, height - thickness * 2f32);
+    }
 
-        ctx.draw(0, self.indices.len() as i32, 1);
+    fn draw_quad(&mut self, corners: [Vec2; 4]) {
+        for corner in corners {
+            self.vertices.push(Vertex { pos: corner, color: self.color });
+        }
 
+        let i: i32 = self.vertices.len() as i32;
+        self.indices.extend_from_slice(&[i - 4, i - 3, i - 2, i - 2, i - 4, i - 1]);
     }
 
     pub fn get_num_indices(&self) -> i32 {
@@ -136,10 +212,266 @@ impl ShapeBatch {
     }
 }
 
-struct ShapeRenderer {
-    batches: Vec<ShapeBatch>
+fn circle_point(segment: usize, segments: usize) -> Vec2 {
+    let angle = segment as f32 / segments as f32 * std::f32::consts::TAU;
+
+    Vec2::new(angle.cos(), angle.sin())
+}
+
+/// Draws any number of shapes, chaining a new batch whenever the current one is full.
+/// Every batch keeps its GPU buffers between frames and only updates their contents.
+pub struct ShapeRenderer {
+    batches: Vec<ShapeBatch>,
+    bindings: Vec<Bindings>,
+    current: usize,
+    color: Color,
+
+    pipeline: Pipeline,
+    blend_mode: BlendMode,
+    max_batch_size: usize,
+    draw_calls: usize
 }
 
 impl ShapeRenderer {
-    
-}
\ No newline at end of file
+    pub fn new(ctx: &mut Context, max_batch_size: usize) -> Result<Self, EngineError> {
+        let pipeline = create_pipeline(ctx, shader::vertex_shader(), shader::fragment_shader())?;
+
+        Ok(Self {
+            batches: vec![ShapeBatch::new(max_batch_size)],
+            bindings: Vec::new(),
+            current: 0,
+            color: Color { r: 1.0, g: 1.0, b: 1.0, a: 1.0 },
+
+            pipeline,
+            blend_mode: BlendMode::Opaque,
+            max_batch_size,
+            draw_calls: 0
+        })
+    }
+
+    /// Rebuilds the pipeline from new shader sources, keeping the old one if they don't compile
+    pub fn reload_shader(&mut self, ctx: &mut Context, vertex: &str, fragment: &str) -> Result<(), EngineError> {
+        self.pipeline = create_pipeline(ctx, vertex, fragment)?;
+        self.pipeline.set_blend(ctx, self.blend_mode.blend_state());
+
+        Ok(())
+    }
+
+    pub fn set_blend_mode(&mut self, ctx: &mut Context, blend_mode: BlendMode) {
+        self.pipeline.set_blend(ctx, blend_mode.blend_state());
+        self.blend_mode = blend_mode;
+    }
+
+    pub fn get_blend_mode(&self) -> BlendMode {
+        self.blend_mode
+    }
+
+    pub fn begin(&mut self) {
+        for batch in &mut self.batches {
+            batch.begin();
+        }
+
+        self.current = 0;
+    }
+
+    pub fn set_color(&mut self, r: f32, g: f32, b: f32, a: f32) {
+        self.color = Color {r, g, b , a};
+    }
+
+    /// The batch the next shape goes into, moving on to the next ones until it fits
+    fn batch_for(&mut self, vertices: usize) -> &mut ShapeBatch {
+        while !self.batches[self.current].has_room(vertices) {
+            self.current += 1;
+
+            if self.current == self.batches.len() {
+                self.batches.push(ShapeBatch::new(self.max_batch_size.max(vertices)));
+            }
+        }
+
+        let batch = &mut self.batches[self.current];
+        batch.color = self.color;
+
+        batch
+    }
+
+    pub fn draw_triangle(&mut self, x: f32, y: f32, x1: f32, y1: f32, x2: f32, y2: f32) {
+        self.batch_for(3).draw_triangle(x, y, x1, y1, x2, y2);
+    }
+
+    pub fn draw_rect(&mut self, x: f32, y: f32, width: f32, height: f32) {
+        self.batch_for(4).draw_rect(x, y, width, height);
+    }
+
+    pub fn draw_line(&mut self, x0: f32, y0: f32, x1: f32, y1: f32, thickness: f32) {
+        self.batch_for(4).draw_line(x0, y0, x1, y1, thickness);
+    }
+
+    pub fn draw_circle(&mut self, x: f32, y: f32, radius: f32, segments: usize) {
+        self.batch_for(segments.max(3) + 1).draw_circle(x, y, radius, segments);
+    }
+
+    pub fn draw_ring(&mut self, x: f32, y: f32, radius: f32, thickness: f32, segments: usize) {
+        self.batch_for(segments.max(3) * 2).draw_ring(x, y, radius, thickness, segments);
+    }
+
+    pub fn draw_polygon(&mut self, points: &[Vec2]) {
+        self.batch_for(points.len()).draw_polygon(points);
+    }
+
+    pub fn draw_rect_outline(&mut self, x: f32, y: f32, width: f32, height: f32, thickness: f32) {
+        self.batch_for(16).draw_rect_outline(x, y, width, height, thickness);
+    }
+
+    /// Uploads and draws every batch that has shapes in it
+    pub fn end(&mut self, ctx: &mut Context, camera: &mut Camera) {
+        self.draw_calls = 0;
+
+        for (i, batch) in self.batches.iter().enumerate().take(self.current + 1) {
+            if batch.is_empty() {
+                continue;
+            }
+
+            // buffers are created once per batch and sized for its capacity
+            while self.bindings.len() <= i {
+                let size = self.batches[self.bindings.len()].max_batch_size;
+
+                let vertex_buffer = Buffer::stream(ctx, BufferType::VertexBuffer, size * std::mem::size_of::<Vertex>());
+                let index_buffer = Buffer::index_stream(ctx, IndexType::Int, size * INDICES_PER_VERTEX * std::mem::size_of::<i32>());
+
+                self.bindings.push(Bindings {
+                    vertex_buffers: vec![vertex_buffer],
+                    index_buffer,
+                    images: vec![],
+                });
+            }
+
+            let bindings = &self.bindings[i];
+            bindings.vertex_buffers[0].update(ctx, &batch.vertices);
+            bindings.index_buffer.update(ctx, &batch.indices);
+
+            ctx.apply_pipeline(&self.pipeline);
+            ctx.apply_bindings(bindings);
+            ctx.apply_uniforms(&shader::Uniforms { uProjection: camera.get_proj_matrix(), uView: camera.get_view_matrix()});
+
+            ctx.draw(0, batch.indices.len() as i32, 1);
+
+            self.draw_calls += 1;
+        }
+    }
+
+    pub fn get_draw_calls(&self) -> usize {
+        self.draw_calls
+    }
+
+    pub fn get_num_indices(&self) -> i32 {
+        self.batches.iter().map(|batch| batch.get_num_indices()).sum()
+    }
+
+    pub fn get_num_vertices(&self) -> i32 {
+        self.batches.iter().map(|batch| batch.get_num_vertices()).sum()
+    }
+}
+
+fn create_pipeline(ctx: &mut Context, vertex: &str, fragment: &str) -> Result<Pipeline, EngineError> {
+    let shader = Shader::new(ctx, vertex, fragment, shader::meta()).map_err(|source| EngineError::Shader { name: "shape", source })?;
+
+    Ok(Pipeline::new(
+        ctx,
+        &[BufferLayout::default()],
+        &[VertexAttribute::with_buffer("pos", VertexFormat::Float2, 0),
+        VertexAttribute::with_buffer("color", VertexFormat::Float4, 0)],
+        shader
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn positions(batch: &ShapeBatch) -> Vec<Vec2> {
+        batch.vertices.iter().map(|vertex| vertex.pos).collect()
+    }
+
+    fn assert_indices_in_range(batch: &ShapeBatch) {
+        assert_eq!(batch.indices.len() % 3, 0);
+        assert!(batch.indices.iter().all(|&i| i >= 0 && (i as usize) < batch.vertices.len()));
+    }
+
+    #[test]
+    fn line_is_a_quad_around_the_segment() {
+        let mut batch = ShapeBatch::new(64);
+        batch.draw_line(0.0, 0.0, 10.0, 0.0, 2.0);
+
+        assert_eq!(positions(&batch), vec![Vec2::new(0.0, 1.0), Vec2::new(0.0, -1.0), Vec2::new(10.0, -1.0), Vec2::new(10.0, 1.0)]);
+        assert_eq!(batch.indices, vec![0, 1, 2, 2, 0, 3]);
+    }
+
+    #[test]
+    fn circle_is_a_fan_of_segments() {
+        let mut batch = ShapeBatch::new(64);
+        batch.draw_circle(5.0, 5.0, 2.0, 8);
+
+        assert_eq!(batch.get_num_vertices(), 9);
+        assert_eq!(batch.get_num_indices(), 8 * 3);
+        assert_indices_in_range(&batch);
+
+        assert_eq!(batch.vertices[0].pos, Vec2::new(5.0, 5.0));
+        assert!(batch.vertices[1..].iter().all(|vertex| ((vertex.pos - Vec2::new(5.0, 5.0)).length() - 2.0).abs() < 1e-5));
+    }
+
+    #[test]
+    fn ring_has_inner_and_outer_points() {
+        let mut batch = ShapeBatch::new(64);
+        batch.draw_ring(0.0, 0.0, 4.0, 1.0, 6);
+
+        assert_eq!(batch.get_num_vertices(), 12);
+        assert_eq!(batch.get_num_indices(), 6 * 6);
+        assert_indices_in_range(&batch);
+
+        for (i, vertex) in batch.vertices.iter().enumerate() {
+            let expected = if i % 2 == 0 { 4.0 } else { 3.0 };
+            assert!((vertex.pos.length() - expected).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn polygon_is_triangulated_as_a_fan() {
+        let mut batch = ShapeBatch::new(64);
+        batch.draw_polygon(&[Vec2::new(0.0, 0.0), Vec2::new(1.0, 0.0), Vec2::new(1.0, 1.0), Vec2::new(0.5, 2.0), Vec2::new(0.0, 1.0)]);
+
+        assert_eq!(batch.get_num_vertices(), 5);
+        assert_eq!(batch.indices, vec![0, 1, 2, 0, 2, 3, 0, 3, 4]);
+
+        batch.draw_polygon(&[Vec2::ZERO, Vec2::ONE]);
+        assert_eq!(batch.get_num_vertices(), 5);
+    }
+
+    #[test]
+    fn rect_outline_stays_inside_the_rect() {
+        let mut batch = ShapeBatch::new(64);
+        batch.draw_rect_outline(10.0, 20.0, 30.0, 40.0, 2.0);
+
+        assert_eq!(batch.get_num_vertices(), 16);
+        assert_eq!(batch.get_num_indices(), 24);
+        assert_indices_in_range(&batch);
+        assert!(batch.vertices.iter().all(|vertex| (10.0..=40.0).contains(&vertex.pos.x) && (20.0..=60.0).contains(&vertex.pos.y)));
+    }
+
+    #[test]
+    fn indices_continue_after_previous_shapes() {
+        let mut batch = ShapeBatch::new(64);
+        batch.draw_rect(0.0, 0.0, 1.0, 1.0);
+        batch.draw_line(0.0, 0.0, 0.0, 1.0, 1.0);
+
+        assert_eq!(&batch.indices[6..], &[4, 5, 6, 6, 4, 7]);
+    }
+
+    #[test]
+    fn shapes_fit_the_index_bound() {
+        let mut batch = ShapeBatch::new(64);
+        batch.draw_ring(0.0, 0.0, 1.0, 0.5, 3);
+        batch.draw_circle(0.0, 0.0, 1.0, 3);
+
+        assert!(batch.indices.len() <= batch.vertices.len() * INDICES_PER_VERTEX);
+    }
+}
@@ -0,0 +1,9 @@
+#version 140
+
+in vec2 fUv;
+
+uniform sampler2D tex;
+
+void main() {
+    gl_FragColor = texture(tex, fUv);
+}
\ No newline at end of file
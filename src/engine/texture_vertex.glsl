@@ -0,0 +1,14 @@
+#version 140
+in vec2 pos;
+in vec2 uv;
+
+out vec2 fUv;
+
+uniform mat4 uProjection;
+uniform mat4 uView;
+
+void main() {
+    gl_Position = uProjection * uView * vec4(pos, 0.0, 1.0);
+
+    fUv = uv;
+}
\ No newline at end of file
@@ -0,0 +1,93 @@
+/// Turns variable frame times into a whole number of fixed length simulation ticks
+pub struct FixedTimestep {
+    pub ticks_per_second: f32,
+    /// Most ticks run in a single frame at 1x speed, any time beyond that is dropped instead of caught up later.
+    /// The limit grows with `speed` so fast forwarding is not capped on low refresh rate displays
+    pub max_steps: u32,
+    pub speed: f32,
+
+    accumulator: f32
+}
+
+impl FixedTimestep {
+    pub fn new(ticks_per_second: f32, max_steps: u32) -> Self {
+        Self { ticks_per_second, max_steps, speed: 1f32, accumulator: 0f32 }
+    }
+
+    /// Adds a frame of `dt` seconds, scaled by `speed`, and returns how many ticks are due
+    pub fn advance(&mut self, dt: f32) -> u32 {
+        let step = 1f32 / self.ticks_per_second;
+
+        self.accumulator += dt * self.speed;
+
+        let max_steps = self.max_steps * self.speed.ceil().max(1f32) as u32;
+
+        let steps = (self.accumulator / step) as u32;
+        if steps > max_steps {
+            self.accumulator = 0f32;
+            return max_steps;
+        }
+
+        self.accumulator -= steps as f32 * step;
+        steps
+    }
+
+    pub fn reset(&mut self) {
+        self.accumulator = 0f32;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Ticks run over `seconds` of frames at `fps`
+    fn ticks_over(timestep: &mut FixedTimestep, fps: f32, seconds: f32) -> u32 {
+        (0..(fps * seconds).round() as u32).map(|_| timestep.advance(1f32 / fps)).sum()
+    }
+
+    #[test]
+    fn carries_leftover_time_to_the_next_frame() {
+        let mut timestep = FixedTimestep::new(60f32, 4);
+
+        assert_eq!(timestep.advance(0.5 / 60f32), 0);
+        assert_eq!(timestep.advance(0.8 / 60f32), 1);
+        assert_eq!(timestep.advance(0.8 / 60f32), 1);
+    }
+
+    #[test]
+    fn tick_rate_does_not_depend_on_the_frame_rate() {
+        for speed in [0.25f32, 1f32, 3f32, 8f32] {
+            for fps in [30f32, 60f32, 144f32] {
+                let mut timestep = FixedTimestep::new(60f32, 4);
+                timestep.speed = speed;
+
+                let ticks = ticks_over(&mut timestep, fps, 10f32) as f32;
+                let expected = 60f32 * speed * 10f32;
+
+                assert!((ticks - expected).abs() <= 1f32, "{}x at {} fps ran {} ticks instead of {}", speed, fps, ticks, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn long_frames_are_capped_and_dropped() {
+        let mut timestep = FixedTimestep::new(60f32, 4);
+
+        assert_eq!(timestep.advance(1f32), 4);
+        assert_eq!(timestep.advance(0f32), 0);
+
+        timestep.speed = 2.5;
+        assert_eq!(timestep.advance(1f32), 12);
+    }
+
+    #[test]
+    fn reset_discards_accumulated_time() {
+        let mut timestep = FixedTimestep::new(60f32, 4);
+
+        timestep.advance(0.9 / 60f32);
+        timestep.reset();
+
+        assert_eq!(timestep.advance(0.5 / 60f32), 0);
+    }
+}
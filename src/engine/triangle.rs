@@ -5,14 +5,14 @@ use std::vec;
 use glam::{Vec2, Vec3};
 use miniquad::*;
 
-use super::shader;
+use super::{shader, error::EngineError};
 
 struct Vertex {
     pos: Vec2,
     color: Vec3
 }
 
-pub fn create_triangle(ctx: &mut Context) -> (Pipeline, Bindings){
+pub fn create_triangle(ctx: &mut Context) -> Result<(Pipeline, Bindings), EngineError> {
     #[rustfmt::skip]
     let vertices: [Vertex; 3] = [
         Vertex { pos: Vec2 { x: -100f32, y: -100f32 }, color: Vec3 { x: 1.0, y: 0.0, z: 0.0 }},
@@ -30,7 +30,8 @@ pub fn create_triangle(ctx: &mut Context) -> (Pipeline, Bindings){
         images: vec![],
     };
 
-    let shader = Shader::new(ctx, &shader::vertex_shader(), &shader::fragment_shader(), shader::meta()).unwrap();
+    let shader = Shader::new(ctx, shader::vertex_shader(), shader::fragment_shader(), shader::meta())
+        .map_err(|source| EngineError::Shader { name: "triangle", source })?;
 
     let pipeline = Pipeline::new(
         ctx, 
@@ -40,5 +41,5 @@ pub fn create_triangle(ctx: &mut Context) -> (Pipeline, Bindings){
         shader
     );
 
-    (pipeline, bindings)
+    Ok((pipeline, bindings))
 }
\ No newline at end of file
@@ -1,4 +1,6 @@
-use crate::{engine::shape_renderer::ShapeBatch, heap_array};
+use glam::Vec2;
+
+use crate::{colormap::HeatMapSettings, engine::pixel_renderer::PixelBuffer, heap_array, statistics::{Statistics, HEAT_BINS}};
 
 
 pub const COLS: i32 = 220;
@@ -13,7 +15,8 @@ pub struct CellGrid {
     cells: Box<[[Cell; ROWS as usize]; COLS as usize]>,
 
     swaps: Vec<Swap>,
-    num_of_swaps: u32
+    num_of_swaps: usize,
+    num_of_ticks: u64
 }
 
 impl CellGrid {
@@ -26,7 +29,8 @@ impl CellGrid {
 
             cells: heap_array::create_cells_array(),
             swaps: Vec::new(),
-            num_of_swaps: 0
+            num_of_swaps: 0,
+            num_of_ticks: 0
         }
     }
     
@@ -44,35 +48,30 @@ impl CellGrid {
         }
     }
 
-    pub fn render(&self, shape_renderer: &mut ShapeBatch) {
-        for i in 0..self.cols {
-            for j in 0..self.rows {
-
-                let color = rgb(self.cells[i as usize][j as usize].element_data.color.0, self.cells[i as usize][j as usize].element_data.color.1, self.cells[i as usize][j as usize].element_data.color.2, 
-                    self.cells[i as usize][j as usize].element_data.color.3);
+    pub fn clear(&mut self) {
+        self.cells = heap_array::create_cells_array();
+        self.swaps.clear();
+        self.num_of_swaps = 0;
+        self.num_of_ticks = 0;
+    }
 
-                if color.3 != 0.0 {
-                    shape_renderer.set_color(color.0, color.1, color.2, color.3);
-                    shape_renderer.draw_rect(i as f32 * self.size, j as f32 * self.size + 250f32, self.size, self.size);
-                }
+    /// Writes the color of every cell into the pixel buffer, one texel per cell
+    pub fn render(&self, buffer: &mut PixelBuffer) {
+        for i in 0..self.cols as usize {
+            for j in 0..self.rows as usize {
+                buffer.set_pixel(i, j, self.cells[i][j].element_data.color);
             }
         }
     }
 
-    pub fn render_heatmap(&self, shape_renderer: &mut ShapeBatch) {
-        for i in 0..self.cols {
-            for j in 0..self.rows {
-                
-                if self.cells[i as usize][j as usize].heat_value >= 0f32 {
-                    let r = map(&self.cells[i as usize][j as usize].heat_value, 0f32, 2000f32, 0f32, 255f32);
-                    shape_renderer.set_color(r, 1f32 - r, 0.0, 1.0); 
-                } else {
-                    let r = map(&self.cells[i as usize][j as usize].heat_value, 0f32, -2000f32, 0f32, 255f32);
-                    shape_renderer.set_color(0.0, 1.0 - r, r, 1.0);
-                }
-                
-                
-                shape_renderer.draw_rect(i as f32 * self.size, j as f32 * self.size + 250f32, self.size, self.size);
+    pub fn render_heatmap(&self, buffer: &mut PixelBuffer, settings: &HeatMapSettings) {
+        let alpha = (settings.opacity * 255f32) as u8;
+
+        for i in 0..self.cols as usize {
+            for j in 0..self.rows as usize {
+                let (r, g, b) = settings.color(self.cells[i][j].heat_value);
+
+                buffer.set_pixel(i, j, ((r * 255f32) as u8, (g * 255f32) as u8, (b * 255f32) as u8, alpha));
             }
         }
     }
@@ -106,10 +105,8 @@ impl CellGrid {
                     CellType::Steam => {
                         self.gas_movement(i, j, 0.33);
                         
-                        if self.cells[i][j].heat_value < 1f32 {
-                            if 0.001 > fastrand::f32() {
-                                self.cells[i][j].element_data = ElementData::water_element();
-                            }
+                        if self.cells[i][j].heat_value < 1f32 && 0.001 > fastrand::f32() {
+                            self.cells[i][j].element_data = ElementData::water_element();
                         }
                         
                         if self.cells[i][j].heat_value < -50f32 {
@@ -228,7 +225,8 @@ impl CellGrid {
             }
         }
 
-        self.num_of_swaps = self.swaps.len().try_into().unwrap();
+        self.num_of_swaps = self.swaps.len();
+        self.num_of_ticks += 1;
 
         let swaps = self.swaps.clone();
         self.swaps.clear();
@@ -239,25 +237,25 @@ impl CellGrid {
 
     }
 
-    fn swap(&mut self, i1: u32, j1: u32, i2: u32, j2: u32) {
-        if i1 < self.cols && i2 < self.cols && j1 < self.rows && j2 < self.rows {
+    fn swap(&mut self, i1: usize, j1: usize, i2: usize, j2: usize) {
+        if i1 < self.cols as usize && i2 < self.cols as usize && j1 < self.rows as usize && j2 < self.rows as usize {
 
-            let temp = self.cells[i2 as usize][j2 as usize].clone();
+            let temp = self.cells[i2][j2];
 
-            self.cells[i2 as usize][j2 as usize] = self.cells[i1 as usize][j1 as usize];
-            self.cells[i1 as usize][j1 as usize] = temp;
+            self.cells[i2][j2] = self.cells[i1][j1];
+            self.cells[i1][j1] = temp;
         }
     }
 
     fn falling_sand(&mut self, i: usize, j: usize) -> bool {
         if self.cells[i][j - 1].element_data.state != State::Solid {
-            self.swaps.push(Swap::new_usize(i, j, i, j - 1));
+            self.swaps.push(Swap::new(i, j, i, j - 1));
             return true;
         } else if self.cells[i + 1][j - 1].element_data.state != State::Solid  && self.cells[i + 1][j].element_data.state != State::Solid {
-            self.swaps.push(Swap::new_usize(i, j, i + 1, j - 1));
+            self.swaps.push(Swap::new(i, j, i + 1, j - 1));
             return true;
         } else if self.cells[i - 1][j - 1].element_data.state != State::Solid && self.cells[i - 1][j].element_data.state != State::Solid {
-            self.swaps.push(Swap::new_usize(i, j, i - 1, j - 1));
+            self.swaps.push(Swap::new(i, j, i - 1, j - 1));
             return true;
         }
         false
@@ -265,30 +263,30 @@ impl CellGrid {
 
     fn liquid_movement(&mut self, i: usize, j: usize) {
         if self.cells[i][j - 1].element_data.state == State::Gas || self.cells[i][j - 1].element_data.state == State::Plasma  {
-            self.swaps.push(Swap::new_usize(i, j, i, j - 1));
+            self.swaps.push(Swap::new(i, j, i, j - 1));
             
         } else if self.cells[i + 1][j - 1].element_data.state == State::Gas  && self.cells[i + 1][j].element_data.state == State::Gas
             || self.cells[i - 1][j - 1].element_data.state == State::Gas && self.cells[i - 1][j].element_data.state == State::Gas {
             if fastrand::bool() {
                 if self.cells[i + 1][j - 1].element_data.state == State::Gas  && self.cells[i + 1][j].element_data.state == State::Gas {
-                    self.swaps.push(Swap::new_usize(i, j, i + 1, j - 1))
+                    self.swaps.push(Swap::new(i, j, i + 1, j - 1))
                 } else {
-                    self.swaps.push(Swap::new_usize(i, j, i - 1, j - 1));
+                    self.swaps.push(Swap::new(i, j, i - 1, j - 1));
                 }
             } else {
                 if self.cells[i - 1][j - 1].element_data.state == State::Gas  && self.cells[i - 1][j].element_data.state == State::Gas {
-                    self.swaps.push(Swap::new_usize(i, j, i - 1, j - 1))
+                    self.swaps.push(Swap::new(i, j, i - 1, j - 1))
                 } else {
-                    self.swaps.push(Swap::new_usize(i, j, i + 1, j - 1));
+                    self.swaps.push(Swap::new(i, j, i + 1, j - 1));
                 }
             }
         } else if fastrand::bool() {
             if self.cells[i + 1][j].element_data.state != State::Solid {
-                self.swaps.push(Swap::new_usize(i, j, i + 1, j));
+                self.swaps.push(Swap::new(i, j, i + 1, j));
             }
         } else {
             if self.cells[i - 1][j].element_data.state != State::Solid {
-                self.swaps.push(Swap::new_usize(i, j, i - 1, j));
+                self.swaps.push(Swap::new(i, j, i - 1, j));
             }
         }
     }
@@ -297,7 +295,7 @@ impl CellGrid {
         let mut offset = 0;
 
         if self.cells[i][j + 1].element_data.state == State::Gas && self.cells[i][j + 1].element_data.cell_type != self.cells[i][j].element_data.cell_type {
-            self.swaps.push(Swap::new_usize(i, j, i, j + 1));
+            self.swaps.push(Swap::new(i, j, i, j + 1));
 
             offset = 1;
         }
@@ -305,12 +303,10 @@ impl CellGrid {
         let r = fastrand::f32();
         if r < spread {
             if self.cells[i - 1][j + offset].element_data.state == State::Gas && self.cells[i][j + offset].element_data.cell_type != self.cells[i - 1][j + offset].element_data.cell_type {
-                self.swaps.push(Swap::new_usize(i, j + offset, i - 1, j + offset));
-            }
-        } else if r > 1f32 - spread {
-            if self.cells[i + 1][j + offset].element_data.state == State::Gas && self.cells[i][j + offset].element_data.cell_type != self.cells[i + 1][j + offset].element_data.cell_type {
-                self.swaps.push(Swap::new_usize(i, j + offset, i + 1, j + offset));
+                self.swaps.push(Swap::new(i, j + offset, i - 1, j + offset));
             }
+        } else if r > 1f32 - spread && self.cells[i + 1][j + offset].element_data.state == State::Gas && self.cells[i][j + offset].element_data.cell_type != self.cells[i + 1][j + offset].element_data.cell_type {
+            self.swaps.push(Swap::new(i, j + offset, i + 1, j + offset));
         }
     }
     
@@ -329,24 +325,123 @@ impl CellGrid {
         }
     }
         
-    pub fn modify_cell(&mut self, x: i32, y: i32, cell: Cell, brush_size: i32) {
-        for i in 0..brush_size {
-            for j in 0..brush_size {
-                if x + i > 0 && x + i < self.cols as i32 - 1 && y + j > 0 && y + j < self.rows as i32 - 1 {
-                    self.cells[(x + i) as usize][(y + j) as usize] = cell.clone();
-                }
+    pub fn modify_cell(&mut self, positions: &[(i32, i32)], cell: Cell) {
+        for &(i, j) in positions {
+            if self.in_interior(i, j) {
+                self.cells[i as usize][j as usize] = cell;
+            }
+        }
+    }
+
+    /// Adds `delta` to the heat of the cells without changing their element
+    pub fn add_heat(&mut self, positions: &[(i32, i32)], delta: f32) {
+        for &(i, j) in positions {
+            if self.in_interior(i, j) {
+                self.cells[i as usize][j as usize].heat_value += delta;
             }
         }
     }
 
-    pub fn num_of_swaps(&self) -> &u32 {
+    /// Sets the heat of the cells without changing their element
+    pub fn set_heat(&mut self, positions: &[(i32, i32)], heat_value: f32) {
+        for &(i, j) in positions {
+            if self.in_interior(i, j) {
+                self.cells[i as usize][j as usize].heat_value = heat_value;
+            }
+        }
+    }
+
+    /// Every cell position inside the borders
+    pub fn interior_positions(&self) -> Vec<(i32, i32)> {
+        (1..self.cols as i32 - 1).flat_map(|i| (1..self.rows as i32 - 1).map(move |j| (i, j))).collect()
+    }
+
+    /// Lowest and highest cell position inside the borders
+    pub fn interior_bounds(&self) -> ((i32, i32), (i32, i32)) {
+        ((1, 1), (self.cols as i32 - 2, self.rows as i32 - 2))
+    }
+
+    pub fn in_interior(&self, i: i32, j: i32) -> bool {
+        i > 0 && i < self.cols as i32 - 1 && j > 0 && j < self.rows as i32 - 1
+    }
+
+    pub fn get_cell(&self, i: i32, j: i32) -> Option<Cell> {
+        if i >= 0 && i < self.cols as i32 && j >= 0 && j < self.rows as i32 {
+            return Some(self.cells[i as usize][j as usize]);
+        }
+
+        None
+    }
+
+    pub fn set_cell(&mut self, i: i32, j: i32, cell: Cell) {
+        if i >= 0 && i < self.cols as i32 && j >= 0 && j < self.rows as i32 {
+            self.cells[i as usize][j as usize] = cell;
+        }
+    }
+
+    pub fn num_of_swaps(&self) -> &usize {
         &self.num_of_swaps
     }
 
-    pub fn get_size(&self) -> &f32 {
-        &self.size
+    pub fn num_of_ticks(&self) -> &u64 {
+        &self.num_of_ticks
     }
-    
+
+    pub fn statistics(&self) -> Statistics {
+        let mut statistics = Statistics {
+            tick: self.num_of_ticks,
+            counts: [0; CellType::ALL.len()],
+            min_heat: f32::MAX,
+            max_heat: f32::MIN,
+            mean_heat: 0f32,
+            total_heat: 0f64,
+            burning: 0,
+            heat_histogram: [0; HEAT_BINS]
+        };
+
+        for cell in self.cells.iter().flatten() {
+            statistics.counts[cell.element_data.cell_type as usize] += 1;
+            statistics.min_heat = statistics.min_heat.min(cell.heat_value);
+            statistics.max_heat = statistics.max_heat.max(cell.heat_value);
+            statistics.total_heat += cell.heat_value as f64;
+            statistics.heat_histogram[Statistics::heat_bin(cell.heat_value)] += 1;
+
+            if cell.active {
+                statistics.burning += 1;
+            }
+        }
+
+        statistics.mean_heat = (statistics.total_heat / (self.cols * self.rows) as f64) as f32;
+
+        statistics
+    }
+
+    pub fn count_of(&self, cell_type: CellType) -> u32 {
+        self.cells.iter().flatten().filter(|cell| cell.element_data.cell_type == cell_type).count() as u32
+    }
+
+    pub fn max_heat(&self) -> f32 {
+        self.heat_range().1
+    }
+
+    pub fn heat_range(&self) -> (f32, f32) {
+        self.cells.iter().flatten().fold((f32::MAX, f32::MIN), |(min, max), cell| (min.min(cell.heat_value), max.max(cell.heat_value)))
+    }
+
+    /// Cell coordinates containing a world position, which may lie outside the grid
+    pub fn cell_at_world(&self, pos: Vec2) -> (i32, i32) {
+        ((pos.x / self.size).floor() as i32, (pos.y / self.size).floor() as i32)
+    }
+
+    pub fn cell_size(&self) -> f32 {
+        self.size
+    }
+
+    /// Size of the whole grid in world units, with its lower left corner at the origin
+    pub fn world_size(&self) -> Vec2 {
+        Vec2::new(self.cols as f32 * self.size, self.rows as f32 * self.size)
+    }
+
     pub fn get_element_on_mouse(&self, i: i32, j: i32) -> Option<CellType> {
         if i >= 0 && i < self.cols as i32 && j >= 0 && j < self.rows as i32 {
             return Some(self.cells[i as usize][j as usize].element_data.cell_type);
@@ -355,29 +450,46 @@ impl CellGrid {
         None
     }
 
+    /// FNV-1a hash over every field of every cell, used to check that a replay reproduced the recorded state
+    pub fn checksum(&self) -> u64 {
+        let mut hash: u64 = 0xcbf29ce484222325;
+        let mut write = |value: u64| {
+            for byte in value.to_le_bytes() {
+                hash ^= byte as u64;
+                hash = hash.wrapping_mul(0x100000001b3);
+            }
+        };
+
+        for column in self.cells.iter() {
+            for cell in column.iter() {
+                let data = &cell.element_data;
+
+                write(data.cell_type as u64);
+                write(u32::from_le_bytes([data.color.0, data.color.1, data.color.2, data.color.3]) as u64);
+                write(data.state as u64);
+                write(data.lifetime as u64);
+                write(data.emitting_heat.to_bits() as u64);
+                write(cell.heat_value.to_bits() as u64);
+                write(cell.active as u64);
+            }
+        }
 
-}
-
-fn map(value: &f32, begin: f32, end: f32, new_begin: f32, new_end: f32) -> f32 {
-    new_begin + (new_end - new_begin) * ((value - begin) / (end - begin))
+        hash
+    }
 }
 
 #[derive(Copy, Clone)]
 struct Swap {
-    i1: u32,
-    j1: u32,
-    i2: u32,
-    j2: u32
+    i1: usize,
+    j1: usize,
+    i2: usize,
+    j2: usize
 }
 
 impl Swap {
-    pub fn new_u32(i1: u32, j1: u32, i2: u32, j2: u32) -> Self {
+    pub fn new(i1: usize, j1: usize, i2: usize, j2: usize) -> Self {
         Self { i1, j1, i2, j2 }
     }
-
-    pub fn new_usize(i1: usize, j1: usize, i2: usize, j2: usize) -> Self {
-        Self { i1: i1.try_into().unwrap(), j1: j1.try_into().unwrap(), i2: i2.try_into().unwrap(), j2: j2.try_into().unwrap() }
-    }
 }
 
 #[derive(Copy, Clone)]
@@ -395,6 +507,30 @@ impl Cell {
     pub fn new(element_data: ElementData) -> Self {
         Self { element_data, heat_value: 0f32, active: false }
     }
+
+    /// Space separated text form of every field, read back by `Cell::parse`
+    pub fn write(&self) -> String {
+        let data = &self.element_data;
+
+        format!("{} {} {} {} {} {} {} {} {} {}", data.cell_type as usize, data.color.0, data.color.1, data.color.2, data.color.3,
+            data.state as usize, data.lifetime, data.emitting_heat, self.heat_value, self.active)
+    }
+
+    pub fn parse<'a>(parts: &mut impl Iterator<Item = &'a str>) -> Option<Self> {
+        let mut next = || parts.next();
+
+        let cell_type = *CellType::ALL.get(next()?.parse::<usize>().ok()?)?;
+        let color = (next()?.parse().ok()?, next()?.parse().ok()?, next()?.parse().ok()?, next()?.parse().ok()?);
+        let state = *State::ALL.get(next()?.parse::<usize>().ok()?)?;
+        let lifetime = next()?.parse().ok()?;
+        let emitting_heat = next()?.parse().ok()?;
+
+        Some(Self {
+            element_data: ElementData { cell_type, color, state, lifetime, emitting_heat },
+            heat_value: next()?.parse().ok()?,
+            active: next()?.parse().ok()?
+        })
+    }
 }
 
 
@@ -404,6 +540,11 @@ pub enum CellType {
         Air, Sand, Solid, Water, Steam, Fire, Coal, SawDust, Methane, Lava, ColdFire, Ice, Gasoline
 }
 
+impl CellType {
+    pub const ALL: [CellType; 13] = [CellType::Air, CellType::Sand, CellType::Solid, CellType::Water, CellType::Steam, CellType::Fire, CellType::Coal,
+                                     CellType::SawDust, CellType::Methane, CellType::Lava, CellType::ColdFire, CellType::Ice, CellType::Gasoline];
+}
+
 #[derive(Copy, Clone)]
 pub struct ElementData {
     pub cell_type: CellType,
@@ -414,6 +555,25 @@ pub struct ElementData {
 }
 
 impl ElementData {
+    /// The element as picked from the palette, fire and cold fire with their default lifetime
+    pub fn for_cell_type(cell_type: CellType) -> Self {
+        match cell_type {
+            CellType::Air => Self::air_element(),
+            CellType::Sand => Self::sand_element(),
+            CellType::Solid => Self::solid_element(),
+            CellType::Water => Self::water_element(),
+            CellType::Steam => Self::steam_element(),
+            CellType::Fire => Self::fire_element(80),
+            CellType::Coal => Self::coal_element(),
+            CellType::SawDust => Self::sawdust_element(),
+            CellType::Methane => Self::methane_element(),
+            CellType::Lava => Self::lava_element(),
+            CellType::ColdFire => Self::coldfire_element(80),
+            CellType::Ice => Self::ice_element(),
+            CellType::Gasoline => Self::gasoline_element()
+        }
+    }
+
     pub fn air_element() -> Self {
         Self { cell_type: CellType::Air, color: (0, 0, 0, 0), state: State::Gas, lifetime: -1, emitting_heat: 0f32 }
     }
@@ -465,12 +625,53 @@ impl ElementData {
     }
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug)]
 #[derive(PartialEq, Eq)]
 pub enum State {
     Solid, Liquid, Gas, Plasma
 }
 
-fn rgb(r: u8, g: u8, b: u8, a: u8) -> (f32, f32, f32, f32) {
-    (r as f32 / 255f32, g as f32 / 255f32, b as f32 / 255f32, a as f32 / 255f32)
+impl State {
+    pub const ALL: [State; 4] = [State::Solid, State::Liquid, State::Gas, State::Plasma];
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn empty_grid() -> CellGrid {
+        let mut cell_grid = CellGrid::new(1f32);
+        cell_grid.clear();
+        cell_grid.set_borders();
+
+        cell_grid
+    }
+
+    #[test]
+    fn checksum_of_an_empty_grid_is_stable() {
+        // saved recordings compare against this, changing the hash breaks all of them
+        assert_eq!(empty_grid().checksum(), 17510359740807138427);
+        assert_eq!(empty_grid().checksum(), empty_grid().checksum());
+    }
+
+    #[test]
+    fn checksum_covers_element_heat_and_active() {
+        let empty = empty_grid().checksum();
+
+        let mut cell_grid = empty_grid();
+        cell_grid.set_cell(5, 5, Cell::new(ElementData::sand_element()));
+        assert_ne!(cell_grid.checksum(), empty);
+
+        let mut cell = Cell::default();
+        cell.heat_value = 1f32;
+        let mut cell_grid = empty_grid();
+        cell_grid.set_cell(5, 5, cell);
+        assert_ne!(cell_grid.checksum(), empty);
+
+        let mut cell = Cell::default();
+        cell.active = true;
+        let mut cell_grid = empty_grid();
+        cell_grid.set_cell(5, 5, cell);
+        assert_ne!(cell_grid.checksum(), empty);
+    }
 }
@@ -0,0 +1,256 @@
+use std::collections::{HashSet, VecDeque};
+
+use crate::grid::{Cell, CellGrid};
+
+pub const DEFAULT_MEMORY_BUDGET: usize = 32 * 1024 * 1024;
+
+#[derive(Copy, Clone)]
+struct Change {
+    i: i32,
+    j: i32,
+    before: Cell,
+    after: Cell
+}
+
+struct Edit {
+    changes: Vec<Change>
+}
+
+impl Edit {
+    fn size_in_bytes(&self) -> usize {
+        std::mem::size_of::<Self>() + self.changes.capacity() * std::mem::size_of::<Change>()
+    }
+}
+
+#[derive(Default)]
+struct Stroke {
+    before: Vec<(i32, i32, Cell)>,
+    touched: HashSet<(i32, i32)>
+}
+
+/// Undo/redo stack of brush strokes. Each stroke keeps the cells it overwrote and what they looked like when
+/// the stroke ended; the oldest strokes are dropped once the stack grows past `memory_budget` bytes.
+pub struct History {
+    undo_stack: VecDeque<Edit>,
+    redo_stack: Vec<Edit>,
+    stroke: Option<Stroke>,
+
+    memory_budget: usize,
+    memory_usage: usize
+}
+
+impl History {
+    pub fn new(memory_budget: usize) -> Self {
+        Self {
+            undo_stack: VecDeque::new(),
+            redo_stack: Vec::new(),
+            stroke: None,
+
+            memory_budget,
+            memory_usage: 0
+        }
+    }
+
+    /// Remembers the current value of every position not yet touched by the open stroke, opening one if needed.
+    /// Must be called before the positions are painted over.
+    pub fn capture(&mut self, cell_grid: &CellGrid, positions: &[(i32, i32)]) {
+        let stroke = self.stroke.get_or_insert_with(Stroke::default);
+
+        for &(i, j) in positions {
+            if stroke.touched.insert((i, j)) {
+                if let Some(cell) = cell_grid.get_cell(i, j) {
+                    stroke.before.push((i, j, cell));
+                }
+            }
+        }
+    }
+
+    pub fn end_stroke(&mut self, cell_grid: &CellGrid) {
+        let stroke = match self.stroke.take() {
+            Some(stroke) if !stroke.before.is_empty() => stroke,
+            _ => return
+        };
+
+        let changes = stroke.before.into_iter().map(|(i, j, before)| Change {
+            i,
+            j,
+            before,
+            after: cell_grid.get_cell(i, j).unwrap_or(before)
+        }).collect();
+
+        self.redo_stack.clear();
+        self.push_undo(Edit { changes });
+    }
+
+    pub fn undo(&mut self, cell_grid: &mut CellGrid) -> bool {
+        self.end_stroke(cell_grid);
+
+        match self.undo_stack.pop_back() {
+            Some(edit) => {
+                self.memory_usage -= edit.size_in_bytes();
+
+                for change in edit.changes.iter().rev() {
+                    cell_grid.set_cell(change.i, change.j, change.before);
+                }
+
+                self.redo_stack.push(edit);
+                true
+            }
+            None => false
+        }
+    }
+
+    pub fn redo(&mut self, cell_grid: &mut CellGrid) -> bool {
+        self.end_stroke(cell_grid);
+
+        match self.redo_stack.pop() {
+            Some(edit) => {
+                for change in edit.changes.iter() {
+                    cell_grid.set_cell(change.i, change.j, change.after);
+                }
+
+                self.push_undo(edit);
+                true
+            }
+            None => false
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+        self.stroke = None;
+        self.memory_usage = 0;
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty() || self.stroke.is_some()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+
+    pub fn num_of_edits(&self) -> usize {
+        self.undo_stack.len()
+    }
+
+    pub fn memory_usage(&self) -> usize {
+        self.memory_usage
+    }
+
+    fn push_undo(&mut self, edit: Edit) {
+        self.memory_usage += edit.size_in_bytes();
+        self.undo_stack.push_back(edit);
+
+        while self.memory_usage > self.memory_budget && self.undo_stack.len() > 1 {
+            if let Some(oldest) = self.undo_stack.pop_front() {
+                self.memory_usage -= oldest.size_in_bytes();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::{CellType, ElementData};
+
+    fn paint(history: &mut History, cell_grid: &mut CellGrid, positions: &[(i32, i32)], cell: Cell) {
+        history.capture(cell_grid, positions);
+        cell_grid.modify_cell(positions, cell);
+    }
+
+    fn sand() -> Cell {
+        Cell::new(ElementData::sand_element())
+    }
+
+    #[test]
+    fn undo_and_redo_restore_the_exact_cells() {
+        let mut cell_grid = CellGrid::new(1f32);
+        let mut history = History::new(DEFAULT_MEMORY_BUDGET);
+        let before = cell_grid.checksum();
+
+        // painting over the same cells twice in one stroke still undoes to what was there before the stroke
+        paint(&mut history, &mut cell_grid, &[(5, 5), (6, 5)], sand());
+        paint(&mut history, &mut cell_grid, &[(6, 5), (7, 5)], Cell::new(ElementData::water_element()));
+        history.end_stroke(&cell_grid);
+        let after = cell_grid.checksum();
+
+        assert_eq!(history.num_of_edits(), 1);
+
+        assert!(history.undo(&mut cell_grid));
+        assert_eq!(cell_grid.checksum(), before);
+
+        assert!(history.redo(&mut cell_grid));
+        assert_eq!(cell_grid.checksum(), after);
+
+        assert!(history.undo(&mut cell_grid));
+        assert!(!history.undo(&mut cell_grid));
+        assert_eq!(cell_grid.checksum(), before);
+    }
+
+    #[test]
+    fn new_stroke_clears_redo() {
+        let mut cell_grid = CellGrid::new(1f32);
+        let mut history = History::new(DEFAULT_MEMORY_BUDGET);
+
+        paint(&mut history, &mut cell_grid, &[(5, 5)], sand());
+        history.end_stroke(&cell_grid);
+        history.undo(&mut cell_grid);
+        assert!(history.can_redo());
+
+        paint(&mut history, &mut cell_grid, &[(8, 8)], sand());
+        history.end_stroke(&cell_grid);
+
+        assert!(!history.can_redo());
+        assert!(!history.redo(&mut cell_grid));
+    }
+
+    #[test]
+    fn empty_strokes_are_not_recorded() {
+        let cell_grid = CellGrid::new(1f32);
+        let mut history = History::new(DEFAULT_MEMORY_BUDGET);
+
+        history.capture(&cell_grid, &[]);
+        history.end_stroke(&cell_grid);
+
+        assert_eq!(history.num_of_edits(), 0);
+        assert!(!history.can_undo());
+    }
+
+    #[test]
+    fn oldest_strokes_are_evicted_past_the_budget() {
+        let one_cell_edit = std::mem::size_of::<Edit>() + std::mem::size_of::<Change>();
+        let budget = 3 * one_cell_edit;
+
+        let mut cell_grid = CellGrid::new(1f32);
+        let mut history = History::new(budget);
+
+        for i in 0..5 {
+            paint(&mut history, &mut cell_grid, &[(5 + i, 5)], sand());
+            history.end_stroke(&cell_grid);
+
+            assert!(history.memory_usage() <= budget);
+        }
+
+        assert_eq!(history.num_of_edits(), 3);
+        assert_eq!(history.memory_usage(), budget);
+
+        // the two oldest strokes are gone, so the first two cells stay painted
+        while history.undo(&mut cell_grid) {}
+        assert_eq!(cell_grid.get_cell(5, 5).unwrap().element_data.cell_type, CellType::Sand);
+        assert_eq!(cell_grid.get_cell(7, 5).unwrap().element_data.cell_type, CellType::Air);
+    }
+
+    #[test]
+    fn a_single_stroke_larger_than_the_budget_is_kept() {
+        let mut cell_grid = CellGrid::new(1f32);
+        let mut history = History::new(1);
+
+        paint(&mut history, &mut cell_grid, &[(5, 5), (6, 6)], sand());
+        history.end_stroke(&cell_grid);
+
+        assert_eq!(history.num_of_edits(), 1);
+    }
+}
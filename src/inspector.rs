@@ -0,0 +1,89 @@
+use egui::{Color32, RichText, Ui};
+
+use crate::grid::{Cell, CellGrid};
+
+/// Every field of the cell at `(i, j)` followed by its 3x3 neighbourhood
+pub fn show(ui: &mut Ui, cell_grid: &CellGrid, i: i32, j: i32) {
+    let cell = match cell_grid.get_cell(i, j) {
+        Some(cell) => cell,
+        None => {
+            ui.label(format!("({}, {}) is outside the grid", i, j));
+            return;
+        }
+    };
+
+    ui.label(format!("position: ({}, {})", i, j));
+    cell_fields(ui, &cell);
+
+    ui.separator();
+    ui.label("neighbours (type / heat)");
+
+    egui::Grid::new("inspector neighbours").striped(true).show(ui, |ui| {
+        for dj in [1, 0, -1] {
+            for di in [-1, 0, 1] {
+                match cell_grid.get_cell(i + di, j + dj) {
+                    Some(neighbour) => {
+                        let text = RichText::new(format!("{:?}\n{:.1}", neighbour.element_data.cell_type, neighbour.heat_value)).color(cell_color(&neighbour));
+
+                        if di == 0 && dj == 0 {
+                            ui.label(text.strong());
+                        } else {
+                            ui.label(text);
+                        }
+                    }
+                    None => {
+                        ui.label("-");
+                    }
+                }
+            }
+            ui.end_row();
+        }
+    });
+}
+
+pub fn cell_fields(ui: &mut Ui, cell: &Cell) {
+    let data = &cell.element_data;
+
+    egui::Grid::new("inspector fields").show(ui, |ui| {
+        ui.label("cell_type");
+        ui.label(format!("{:?}", data.cell_type));
+        ui.end_row();
+
+        ui.label("state");
+        ui.label(format!("{:?}", data.state));
+        ui.end_row();
+
+        ui.label("heat_value");
+        ui.label(format!("{:.3}", cell.heat_value));
+        ui.end_row();
+
+        ui.label("active");
+        ui.label(format!("{}", cell.active));
+        ui.end_row();
+
+        ui.label("lifetime");
+        ui.label(format!("{}", data.lifetime));
+        ui.end_row();
+
+        ui.label("emitting_heat");
+        ui.label(format!("{}", data.emitting_heat));
+        ui.end_row();
+
+        ui.label("color");
+        ui.horizontal(|ui| {
+            ui.label(RichText::new("■").color(cell_color(cell)));
+            ui.label(format!("{:?}", data.color));
+        });
+        ui.end_row();
+    });
+}
+
+fn cell_color(cell: &Cell) -> Color32 {
+    let (r, g, b, a) = cell.element_data.color;
+
+    if a == 0 {
+        Color32::GRAY
+    } else {
+        Color32::from_rgb(r, g, b)
+    }
+}
@@ -0,0 +1,298 @@
+use std::{fs, io, path::Path};
+
+use miniquad::KeyCode;
+
+use crate::{engine::engine::Input, grid::CellType};
+
+pub const KEYBINDINGS_PATH: &str = "keybindings.txt";
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Action {
+    SelectElement(CellType),
+    BrushSizeUp,
+    BrushSizeDown,
+    ToggleHeatMap,
+    /// Toggles `update_simulation`
+    Pause,
+    Step,
+    Clear,
+    Undo,
+    Redo,
+    Pin,
+    Help
+}
+
+impl Action {
+    /// Every action, in the order they are listed in the help overlay
+    pub fn all() -> Vec<Action> {
+        let mut actions: Vec<Action> = CellType::ALL.iter().map(|&cell_type| Action::SelectElement(cell_type)).collect();
+
+        actions.extend_from_slice(&[Action::BrushSizeUp, Action::BrushSizeDown, Action::ToggleHeatMap, Action::Pause, Action::Step,
+            Action::Clear, Action::Undo, Action::Redo, Action::Pin, Action::Help]);
+
+        actions
+    }
+
+    /// Name used in the keybindings file
+    pub fn name(&self) -> String {
+        match self {
+            Action::SelectElement(cell_type) => format!("select_{:?}", cell_type).to_lowercase(),
+            Action::BrushSizeUp => String::from("brush_size_up"),
+            Action::BrushSizeDown => String::from("brush_size_down"),
+            Action::ToggleHeatMap => String::from("toggle_heat_map"),
+            Action::Pause => String::from("pause"),
+            Action::Step => String::from("step"),
+            Action::Clear => String::from("clear"),
+            Action::Undo => String::from("undo"),
+            Action::Redo => String::from("redo"),
+            Action::Pin => String::from("pin"),
+            Action::Help => String::from("help")
+        }
+    }
+
+    pub fn description(&self) -> String {
+        match self {
+            Action::SelectElement(cell_type) => format!("select {:?}", cell_type),
+            Action::BrushSizeUp => String::from("increase brush size"),
+            Action::BrushSizeDown => String::from("decrease brush size"),
+            Action::ToggleHeatMap => String::from("toggle heat map"),
+            Action::Pause => String::from("pause / resume simulation"),
+            Action::Step => String::from("step the simulation"),
+            Action::Clear => String::from("clear the grid"),
+            Action::Undo => String::from("undo"),
+            Action::Redo => String::from("redo"),
+            Action::Pin => String::from("pin the hovered cell"),
+            Action::Help => String::from("show this help")
+        }
+    }
+
+    fn default_binding(&self) -> KeyBinding {
+        let key = match self {
+            Action::SelectElement(CellType::Air) => KeyCode::Key0,
+            Action::SelectElement(CellType::Sand) => KeyCode::Key1,
+            Action::SelectElement(CellType::Water) => KeyCode::Key2,
+            Action::SelectElement(CellType::Steam) => KeyCode::Key3,
+            Action::SelectElement(CellType::Fire) => KeyCode::Key4,
+            Action::SelectElement(CellType::Coal) => KeyCode::Key5,
+            Action::SelectElement(CellType::SawDust) => KeyCode::Key6,
+            Action::SelectElement(CellType::Methane) => KeyCode::Key7,
+            Action::SelectElement(CellType::Lava) => KeyCode::Key8,
+            Action::SelectElement(CellType::ColdFire) => KeyCode::Key9,
+            Action::SelectElement(CellType::Solid) => KeyCode::S,
+            Action::SelectElement(CellType::Ice) => KeyCode::I,
+            Action::SelectElement(CellType::Gasoline) => KeyCode::G,
+            Action::BrushSizeUp => KeyCode::RightBracket,
+            Action::BrushSizeDown => KeyCode::LeftBracket,
+            Action::ToggleHeatMap => KeyCode::H,
+            Action::Pause => KeyCode::Space,
+            Action::Step => KeyCode::Period,
+            Action::Clear => KeyCode::Delete,
+            Action::Undo => return KeyBinding { ctrl: true, ..KeyBinding::new(KeyCode::Z) },
+            Action::Redo => return KeyBinding { ctrl: true, ..KeyBinding::new(KeyCode::Y) },
+            Action::Pin => KeyCode::P,
+            Action::Help => KeyCode::F1
+        };
+
+        KeyBinding::new(key)
+    }
+}
+
+/// A key together with the modifiers that have to be held, other modifiers must be released
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct KeyBinding {
+    pub key: KeyCode,
+    pub ctrl: bool,
+    pub shift: bool,
+    pub alt: bool
+}
+
+impl KeyBinding {
+    pub fn new(key: KeyCode) -> Self {
+        Self { key, ctrl: false, shift: false, alt: false }
+    }
+
+    pub fn is_pressed(&self, input: &Input) -> bool {
+        let modifiers = input.modifiers();
+
+        input.is_key_pressed(self.key) && modifiers.ctrl == self.ctrl && modifiers.shift == self.shift && modifiers.alt == self.alt
+    }
+
+    /// Written like `ctrl+shift+Z`
+    pub fn write(&self) -> String {
+        let mut out = String::new();
+
+        if self.ctrl {
+            out.push_str("ctrl+");
+        }
+        if self.shift {
+            out.push_str("shift+");
+        }
+        if self.alt {
+            out.push_str("alt+");
+        }
+
+        out.push_str(&key_name(self.key));
+
+        out
+    }
+
+    pub fn parse(src: &str) -> Option<Self> {
+        let mut parts: Vec<&str> = src.split('+').collect();
+        let key = parse_key(parts.pop()?)?;
+
+        let mut binding = Self::new(key);
+        for modifier in parts {
+            match modifier.to_lowercase().as_str() {
+                "ctrl" => binding.ctrl = true,
+                "shift" => binding.shift = true,
+                "alt" => binding.alt = true,
+                _ => return None
+            }
+        }
+
+        Some(binding)
+    }
+}
+
+pub struct KeyBindings {
+    bindings: Vec<(Action, Option<KeyBinding>)>
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self { bindings: Action::all().into_iter().map(|action| (action, Some(action.default_binding()))).collect() }
+    }
+}
+
+impl KeyBindings {
+    /// Actions whose binding was pressed this update
+    pub fn pressed(&self, input: &Input) -> Vec<Action> {
+        self.bindings.iter()
+            .filter(|(_, binding)| binding.is_some_and(|binding| binding.is_pressed(input)))
+            .map(|&(action, _)| action)
+            .collect()
+    }
+
+    pub fn bindings(&self) -> &[(Action, Option<KeyBinding>)] {
+        &self.bindings
+    }
+
+    /// One `action key` per line, `action none` leaves the action unbound
+    pub fn write(&self) -> String {
+        let mut out = String::from("# action key, with optional ctrl+ shift+ alt+ modifiers, or none\n");
+
+        for (action, binding) in &self.bindings {
+            let key = binding.map(|binding| binding.write()).unwrap_or_else(|| String::from("none"));
+            out.push_str(&format!("{} {}\n", action.name(), key));
+        }
+
+        out
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        fs::write(path, self.write())
+    }
+
+    /// Starts from the defaults and applies the file on top, along with the errors for lines that could not be read.
+    /// A missing file is not an error.
+    pub fn load(path: impl AsRef<Path>) -> (Self, Vec<String>) {
+        match fs::read_to_string(&path) {
+            Ok(src) => Self::parse(&src),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => (Self::default(), Vec::new()),
+            Err(err) => (Self::default(), vec![err.to_string()])
+        }
+    }
+
+    /// Same as `load` for the contents of a keybindings file
+    pub fn parse(src: &str) -> (Self, Vec<String>) {
+        let mut keybindings = Self::default();
+
+        let mut errors = Vec::new();
+
+        for (line_num, line) in src.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut parts = line.split_whitespace();
+            let (name, key) = (parts.next().unwrap_or_default(), parts.next().unwrap_or_default());
+
+            let binding = if key.eq_ignore_ascii_case("none") { Some(None) } else { KeyBinding::parse(key).map(Some) };
+
+            match (keybindings.bindings.iter_mut().find(|(action, _)| action.name() == name), binding) {
+                (Some(entry), Some(binding)) => entry.1 = binding,
+                _ => errors.push(format!("invalid keybinding line {}: {:?}", line_num + 1, line))
+            }
+        }
+
+        (keybindings, errors)
+    }
+}
+
+const KEYS: [KeyCode; 64] = [
+    KeyCode::Key0, KeyCode::Key1, KeyCode::Key2, KeyCode::Key3, KeyCode::Key4, KeyCode::Key5, KeyCode::Key6, KeyCode::Key7, KeyCode::Key8, KeyCode::Key9,
+    KeyCode::A, KeyCode::B, KeyCode::C, KeyCode::D, KeyCode::E, KeyCode::F, KeyCode::G, KeyCode::H, KeyCode::I, KeyCode::J, KeyCode::K, KeyCode::L, KeyCode::M,
+    KeyCode::N, KeyCode::O, KeyCode::P, KeyCode::Q, KeyCode::R, KeyCode::S, KeyCode::T, KeyCode::U, KeyCode::V, KeyCode::W, KeyCode::X, KeyCode::Y, KeyCode::Z,
+    KeyCode::F1, KeyCode::F2, KeyCode::F3, KeyCode::F4, KeyCode::F5, KeyCode::F6, KeyCode::F7, KeyCode::F8, KeyCode::F9, KeyCode::F10, KeyCode::F11, KeyCode::F12,
+    KeyCode::Space, KeyCode::Apostrophe, KeyCode::Comma, KeyCode::Minus, KeyCode::Period, KeyCode::Slash, KeyCode::Semicolon, KeyCode::Equal,
+    KeyCode::LeftBracket, KeyCode::Backslash, KeyCode::RightBracket, KeyCode::GraveAccent, KeyCode::Tab, KeyCode::Backspace, KeyCode::Insert, KeyCode::Delete
+];
+
+/// Digits are written without the `Key` prefix, everything else by its `KeyCode` name
+fn key_name(key: KeyCode) -> String {
+    let name = format!("{:?}", key);
+
+    name.strip_prefix("Key").map(str::to_string).unwrap_or(name)
+}
+
+fn parse_key(name: &str) -> Option<KeyCode> {
+    KEYS.iter().copied().find(|&key| key_name(key).eq_ignore_ascii_case(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn binding_of(keybindings: &KeyBindings, action: Action) -> Option<KeyBinding> {
+        keybindings.bindings().iter().find(|(other, _)| *other == action).and_then(|&(_, binding)| binding)
+    }
+
+    #[test]
+    fn key_binding_round_trip() {
+        let binding = KeyBinding::parse("ctrl+shift+Z").unwrap();
+
+        assert_eq!(binding, KeyBinding { ctrl: true, shift: true, ..KeyBinding::new(KeyCode::Z) });
+        assert_eq!(binding.write(), "ctrl+shift+Z");
+
+        assert_eq!(KeyBinding::parse("1"), Some(KeyBinding::new(KeyCode::Key1)));
+        assert_eq!(KeyBinding::new(KeyCode::Key1).write(), "1");
+        assert_eq!(KeyBinding::parse("CTRL+alt+leftbracket"), Some(KeyBinding { ctrl: true, alt: true, ..KeyBinding::new(KeyCode::LeftBracket) }));
+    }
+
+    #[test]
+    fn key_binding_rejects_unknown_keys_and_modifiers() {
+        assert_eq!(KeyBinding::parse("super+Z"), None);
+        assert_eq!(KeyBinding::parse("ctrl+"), None);
+        assert_eq!(KeyBinding::parse("Escape"), None);
+    }
+
+    #[test]
+    fn defaults_survive_write_and_parse() {
+        let keybindings = KeyBindings::default();
+        let (parsed, errors) = KeyBindings::parse(&keybindings.write());
+
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!(parsed.bindings(), keybindings.bindings());
+    }
+
+    #[test]
+    fn parse_applies_lines_over_the_defaults() {
+        let (keybindings, errors) = KeyBindings::parse("# comment\n\nundo ctrl+U\nhelp none\nbogus X\npause ctrl+nope\n");
+
+        assert_eq!(binding_of(&keybindings, Action::Undo), Some(KeyBinding { ctrl: true, ..KeyBinding::new(KeyCode::U) }));
+        assert_eq!(binding_of(&keybindings, Action::Help), None);
+        assert_eq!(binding_of(&keybindings, Action::Pause), Some(KeyBinding::new(KeyCode::Space)));
+        assert_eq!(errors.len(), 2);
+    }
+}
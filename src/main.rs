@@ -1,30 +1,158 @@
 #![windows_subsystem = "windows"]
 
+mod breakpoint;
+mod brush;
+mod colormap;
 mod engine;
 mod grid;
 mod heap_array;
+mod history;
+mod inspector;
+mod keybindings;
+mod replay;
+mod stamp;
+mod statistics;
 
-use egui::{Pos2, RichText, Color32};
-use engine::{camera::Camera, shape_renderer::ShapeBatch, engine::Info, engine::start_engine};
-use grid::{CellGrid, Cell, ElementData, COLS};
-use miniquad::Context;
+use breakpoint::{Breakpoint, Condition};
+use brush::{Brush, BrushFilter, BrushModifiers, BrushShape, MAX_BRUSH_SIZE};
+use colormap::{ColorMap, HeatMapSettings};
+use egui::{plot, Pos2, RichText, Color32};
+use glam::Vec2;
+use engine::{camera::Camera, error::EngineError, shader::{self, ShaderWatcher}, pixel_renderer::PixelRenderer, shape_renderer::{BlendMode, ShapeRenderer}, engine::GameLogic, engine::Info, engine::Input, engine::start_engine, timestep::FixedTimestep};
+use grid::{CellGrid, Cell, CellType, ElementData, COLS, ROWS};
+use history::History;
+use keybindings::{Action, KeyBindings, KEYBINDINGS_PATH};
+use miniquad::{Context, MouseButton};
+use replay::{Event, Recorder, Recording, Replay, RECORDING_PATH};
+use stamp::{Stamp, STAMP_DIR};
+use statistics::{Statistics, StatisticsLog, STATISTICS_PATH};
+
+/// How far in pixels the mouse may move between middle press and release for it to count as a click instead of a pan
+const PICK_MAX_DRAG: f32 = 4f32;
 
 fn main() {
-    let game = Game::new();
+    let args: Vec<String> = std::env::args().collect();
+    let replay_path = args.get(2).map(String::as_str).unwrap_or(RECORDING_PATH);
+
+    let mut game = Game::new();
+
+    match args.get(1).map(String::as_str) {
+        Some("--replay-headless") => {
+            replay_headless(game, replay_path);
+            return;
+        }
+        Some("--replay") => game.load_replay(replay_path),
+        Some("--shader-dev") => game.shader_watcher = Some(ShaderWatcher::new(&[shader::VERTEX_SHADER_PATH, shader::FRAGMENT_SHADER_PATH])),
+        _ => ()
+    }
+
+    if let Err(err) = start_engine("sandbox", 750, 1000, false, game) {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    }
+}
+
+fn replay_headless(mut game: Game, path: &str) {
+    let recording = match Recording::load(path) {
+        Ok(recording) => recording,
+        Err(err) => {
+            eprintln!("failed to load {}: {}", path, err);
+            std::process::exit(1);
+        }
+    };
+
+    let num_of_frames = recording.frames.len();
+    game.start_replay(recording);
 
-    start_engine("sandbox", 750, 1000, false, game);
+    while game.replay.is_some() {
+        game.replay_frame();
+    }
+
+    println!("replayed {} frames, checksum {}", num_of_frames, game.cell_grid.checksum());
+
+    match game.replay_result {
+        Some(Some(true)) => println!("final state matches the recording"),
+        Some(Some(false)) => {
+            println!("final state does not match the recording");
+            std::process::exit(1);
+        }
+        _ => println!("recording has no checksum to compare against")
+    }
+}
+
+/// Everything drawn with the graphics context, created in `init`
+struct Renderers {
+    grid: PixelRenderer,
+    heat_map: PixelRenderer,
+    shapes: ShapeRenderer
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum Tool {
+    Brush, Heat, SetTemperature, Select, Paste
 }
 
 pub struct Game {
     camera: Camera,
-    shape_renderer: Option<ShapeBatch>,
-    heat_map_renderer: Option<ShapeBatch>,
+    window_size: Vec2,
+    ui_panel_height: f32,
+    renderers: Option<Renderers>,
+    draw_calls: usize,
+    shader_watcher: Option<ShaderWatcher>,
+    shader_error: Option<String>,
     render_heat_map: bool,
+    heat_map: HeatMapSettings,
 
     cell_grid: CellGrid,
+    tool: Tool,
     selected_cell: Cell,
     brush_size: i32,
-    update_simulation: bool
+    brush_shape: BrushShape,
+    brush_modifiers: BrushModifiers,
+    /// Heat added per second while the heat tool is held
+    heat_rate: f32,
+    temperature: f32,
+    filter_element: CellType,
+    last_mouse_cell: (i32, i32),
+    last_mouse_pos: Vec2,
+    panning: bool,
+    pick_origin: Option<Vec2>,
+    pointer_over_ui: bool,
+    /// The stroke was started with the right button, which erases or cools
+    secondary_stroke: bool,
+    rect_start: Option<(i32, i32)>,
+    update_simulation: bool,
+    timestep: FixedTimestep,
+    ticks_last_frame: u32,
+    step_count: u32,
+    breakpoints: Vec<Breakpoint>,
+    new_condition: Condition,
+    breakpoint_message: String,
+
+    statistics: StatisticsLog,
+    show_statistics: bool,
+    statistics_message: String,
+
+    pinned_cell: Option<(i32, i32)>,
+    inspect_tooltip: bool,
+
+    clipboard: Option<Stamp>,
+    stamps: Vec<Stamp>,
+    stamp_name: String,
+    stamp_message: String,
+
+    keybindings: KeyBindings,
+    keybinding_message: String,
+    show_help: bool,
+
+    history: History,
+    painting: bool,
+    pending_events: Vec<Event>,
+
+    recorder: Option<Recorder>,
+    replay: Option<Replay>,
+    replay_result: Option<Option<bool>>,
+    replay_message: String
 }
 
 impl  Game {
@@ -34,78 +162,683 @@ impl  Game {
 
         Self {
             camera,
-            shape_renderer: None,
-            heat_map_renderer: None,
+            window_size: Vec2::new(750f32, 1000f32),
+            ui_panel_height: 250f32,
+            renderers: None,
+            draw_calls: 0,
+            shader_watcher: None,
+            shader_error: None,
             render_heat_map: false,
+            heat_map: HeatMapSettings::default(),
 
             cell_grid: CellGrid::new(750f32 / COLS as f32),
+            tool: Tool::Brush,
             selected_cell: Cell::new(ElementData::sand_element()),
             brush_size: 8,
-            update_simulation: true
+            brush_shape: BrushShape::Square,
+            brush_modifiers: BrushModifiers::default(),
+            heat_rate: 100f32,
+            temperature: 0f32,
+            filter_element: CellType::Water,
+            last_mouse_cell: (0, 0),
+            last_mouse_pos: Vec2::ZERO,
+            panning: false,
+            pick_origin: None,
+            pointer_over_ui: false,
+            secondary_stroke: false,
+            rect_start: None,
+            update_simulation: true,
+            timestep: FixedTimestep::new(60f32, 4),
+            ticks_last_frame: 0,
+            step_count: 1,
+            breakpoints: Vec::new(),
+            new_condition: Condition::CountAbove { cell_type: CellType::Fire, count: 1000 },
+            breakpoint_message: String::new(),
+
+            statistics: StatisticsLog::new(10000),
+            show_statistics: false,
+            statistics_message: String::new(),
+
+            pinned_cell: None,
+            inspect_tooltip: false,
+
+            clipboard: None,
+            stamps: Vec::new(),
+            stamp_name: String::new(),
+            stamp_message: String::new(),
+
+            keybindings: KeyBindings::default(),
+            keybinding_message: String::new(),
+            show_help: false,
+
+            history: History::new(history::DEFAULT_MEMORY_BUDGET),
+            painting: false,
+            pending_events: Vec::new(),
+
+            recorder: None,
+            replay: None,
+            replay_result: None,
+            replay_message: String::new()
         }
     }
 
-    fn init(&mut self, ctx: &mut Context) {
-       self.shape_renderer = Some(ShapeBatch::new(ctx, 2048 * 40));
-       self.heat_map_renderer = Some(ShapeBatch::new(ctx, 2048 * 40));
+    /// Fits the whole grid into the part of the window above the ui panel
+    fn fit_grid(&mut self) {
+        let viewport_height = (self.window_size.y - self.ui_panel_height).max(1f32);
+
+        self.camera.fit(Vec2::ZERO, self.cell_grid.world_size(), Vec2::ZERO, Vec2::new(self.window_size.x, viewport_height));
+    }
+
+    fn move_camera(&mut self, info: &Info) {
+        let input = &info.input;
+        let mouse_pos = Vec2::new(input.mouse_x, input.mouse_y);
+
+        // drags that start on a window belong to the UI
+        if input.is_button_pressed(MouseButton::Middle) && !info.ui_wants_pointer {
+            self.panning = true;
+        }
+        if !input.is_button_down(MouseButton::Middle) {
+            self.panning = false;
+        }
+
+        if self.panning {
+            self.camera.pan(mouse_pos - self.last_mouse_pos);
+        }
+        self.last_mouse_pos = mouse_pos;
+
+        if input.wheel_dy != 0f32 && !info.ui_wants_pointer {
+            self.camera.zoom_at(mouse_pos, 1.1f32.powf(input.wheel_dy.signum()));
+        }
+    }
+
+    /// Recompiles the shape shaders from the source tree, keeping the error to show instead of panicking
+    fn reload_shaders(&mut self, ctx: &mut Context) {
+        let renderers = match self.renderers.as_mut() {
+            Some(renderers) => renderers,
+            None => return
+        };
+
+        let result = shader::read_shader_sources().and_then(|(vertex, fragment)| renderers.shapes.reload_shader(ctx, &vertex, &fragment));
+
+        self.shader_error = result.err().map(|err| err.to_string());
+    }
+
+    fn mouse_cell(&self, input: &Input) -> (i32, i32) {
+        self.cell_grid.cell_at_world(self.camera.screen_to_world(Vec2::new(input.mouse_x, input.mouse_y)))
+    }
+
+    /// Copies the element and temperature of the cell into `selected_cell`
+    fn pick_cell(&mut self, x: i32, y: i32) {
+        if let Some(cell) = self.cell_grid.get_cell(x, y) {
+            self.selected_cell = Cell::new(cell.element_data);
+            self.selected_cell.heat_value = cell.heat_value;
+        }
+    }
+
+    fn tool_events(&mut self, info: &Info, events: &mut Vec<Event>) {
+        let input = &info.input;
+        let mouse_pos = Vec2::new(input.mouse_x, input.mouse_y);
+        let (x, y) = self.mouse_cell(input);
+        let (last_x, last_y) = self.last_mouse_cell;
+        self.last_mouse_cell = (x, y);
+        self.pointer_over_ui = info.ui_wants_pointer;
+
+        // middle click picks, middle drag pans
+        if input.is_button_pressed(MouseButton::Middle) && !info.ui_wants_pointer {
+            self.pick_origin = Some(mouse_pos);
+        }
+        if input.is_button_released(MouseButton::Middle) && self.pick_origin.take().is_some_and(|origin| origin.distance(mouse_pos) <= PICK_MAX_DRAG) {
+            self.pick_cell(x, y);
+        }
+
+        let alt = input.modifiers().alt;
+        let alt_click = alt && input.is_button_pressed(MouseButton::Left);
+        if alt_click && !self.painting && !info.ui_wants_pointer {
+            self.pick_cell(x, y);
+        }
+
+        // the right button erases with the current brush, or cools with the heat tool
+        let left = input.is_button_down(MouseButton::Left);
+        let right = matches!(self.tool, Tool::Brush | Tool::Heat) && input.is_button_down(MouseButton::Right);
+
+        // a stroke can only start outside the UI, once started it keeps going over windows
+        let down = (left || right) && (self.painting || (!info.ui_wants_pointer && !alt));
+        let pressed = down && !self.painting;
+        let released = !down && self.painting;
+        self.painting = down;
+
+        if pressed {
+            self.rect_start = Some((x, y));
+            self.secondary_stroke = !left;
+        }
+
+        let rect = match self.rect_start {
+            Some(start) if released => {
+                self.rect_start = None;
+                Some(start)
+            }
+            _ => None
+        };
+
+        let brush = match self.brush_shape {
+            BrushShape::Square if down => Some(Brush::Square { x, y, size: self.brush_size }),
+            BrushShape::Circle if down => Some(Brush::Circle { x, y, size: self.brush_size }),
+            BrushShape::Line if pressed => Some(Brush::Line { x0: x, y0: y, x1: x, y1: y, size: self.brush_size }),
+            BrushShape::Line if down => Some(Brush::Line { x0: last_x, y0: last_y, x1: x, y1: y, size: self.brush_size }),
+            BrushShape::Rectangle => rect.map(|(x0, y0)| Brush::Rectangle { x0, y0, x1: x, y1: y }),
+            BrushShape::FloodFill if pressed => Some(Brush::FloodFill { x, y }),
+            _ => None
+        };
+
+        match self.tool {
+            Tool::Brush => {
+                let (modifiers, cell) = if self.secondary_stroke {
+                    (BrushModifiers { filter: BrushFilter::ReplaceAll, ..self.brush_modifiers }, Cell::new(ElementData::air_element()))
+                } else {
+                    (self.brush_modifiers, self.selected_cell)
+                };
+
+                if let Some(brush) = brush {
+                    events.push(Event::Paint { brush, modifiers, cell });
+                }
+            }
+            Tool::Heat => {
+                // held shapes are scaled by the frame time so the rate does not depend on the refresh rate,
+                // shapes applied once on press or release add a full second's worth
+                let seconds = match self.brush_shape {
+                    BrushShape::Rectangle | BrushShape::FloodFill => 1f32,
+                    _ => info.perf.dt
+                };
+                let delta = self.heat_rate * seconds * if self.secondary_stroke { -1f32 } else { 1f32 };
+
+                if let Some(brush) = brush {
+                    events.push(Event::Heat { brush, modifiers: self.brush_modifiers, delta });
+                }
+            }
+            Tool::SetTemperature => {
+                if let Some(brush) = brush {
+                    events.push(Event::SetTemperature { brush, modifiers: self.brush_modifiers, value: self.temperature });
+                }
+            }
+            Tool::Select => {
+                if let Some((x0, y0)) = rect {
+                    self.clipboard = Some(Stamp::copy(&self.cell_grid, x0, y0, x, y));
+                    self.tool = Tool::Paste;
+                }
+            }
+            Tool::Paste => {
+                if let (true, Some(stamp)) = (pressed, self.clipboard.as_ref()) {
+                    events.push(Event::Paste { x, y, stamp: stamp.clone() });
+                }
+            }
+        }
+
+        if released {
+            events.push(Event::EndStroke);
+        }
+    }
+
+    fn hotkey_events(&mut self, info: &Info, events: &mut Vec<Event>) {
+        if info.ui_wants_keyboard {
+            return;
+        }
+
+        for action in self.keybindings.pressed(&info.input) {
+            match action {
+                Action::SelectElement(cell_type) => self.selected_cell = Cell::new(ElementData::for_cell_type(cell_type)),
+                Action::BrushSizeUp => self.brush_size = (self.brush_size + 1).min(MAX_BRUSH_SIZE),
+                Action::BrushSizeDown => self.brush_size = (self.brush_size - 1).max(1),
+                Action::ToggleHeatMap => self.render_heat_map = !self.render_heat_map,
+                Action::Pause => self.update_simulation = !self.update_simulation,
+                Action::Step => events.push(Event::Step(self.step_count)),
+                Action::Clear => {
+                    events.push(Event::Clear);
+                    events.push(Event::EndStroke);
+                }
+                Action::Undo => events.push(Event::Undo),
+                Action::Redo => events.push(Event::Redo),
+                Action::Pin => {
+                    let hovered = self.mouse_cell(&info.input);
+                    self.pinned_cell = if self.pinned_cell == Some(hovered) { None } else { Some(hovered) };
+                }
+                Action::Help => self.show_help = !self.show_help
+            }
+        }
+    }
+
+    fn tick(&mut self, events: &[Event]) {
+        let mut applied = Vec::with_capacity(events.len());
+
+        for event in events {
+            match event {
+                Event::Paint { brush, modifiers, cell } => {
+                    let mut positions = brush.positions(&self.cell_grid);
+                    modifiers.apply(&self.cell_grid, &mut positions);
+
+                    self.history.capture(&self.cell_grid, &positions);
+                    self.cell_grid.modify_cell(&positions, modifiers.paint_cell(*cell));
+                }
+                Event::Heat { brush, modifiers, delta } => {
+                    let mut positions = brush.positions(&self.cell_grid);
+                    modifiers.apply(&self.cell_grid, &mut positions);
+
+                    self.history.capture(&self.cell_grid, &positions);
+                    self.cell_grid.add_heat(&positions, *delta);
+                }
+                Event::SetTemperature { brush, modifiers, value } => {
+                    let mut positions = brush.positions(&self.cell_grid);
+                    modifiers.apply(&self.cell_grid, &mut positions);
+
+                    self.history.capture(&self.cell_grid, &positions);
+                    self.cell_grid.set_heat(&positions, *value);
+                }
+                Event::Clear => {
+                    let positions = self.cell_grid.interior_positions();
+
+                    self.history.capture(&self.cell_grid, &positions);
+                    self.cell_grid.modify_cell(&positions, Cell::default());
+                }
+                Event::Paste { x, y, stamp } => {
+                    let placed: Vec<((i32, i32), Cell)> = stamp.placed_at(*x, *y).into_iter().filter(|&((i, j), _)| self.cell_grid.in_interior(i, j)).collect();
+                    let positions: Vec<(i32, i32)> = placed.iter().map(|&(position, _)| position).collect();
+
+                    self.history.capture(&self.cell_grid, &positions);
+                    for ((i, j), cell) in placed {
+                        self.cell_grid.set_cell(i, j, cell);
+                    }
+                }
+                Event::EndStroke => self.history.end_stroke(&self.cell_grid),
+                Event::Undo => {
+                    self.history.undo(&mut self.cell_grid);
+                }
+                Event::Redo => {
+                    self.history.redo(&mut self.cell_grid);
+                }
+                Event::Step(steps) => {
+                    // a breakpoint can cut the steps short, record how many actually ran
+                    applied.push(Event::Step(self.step(*steps)));
+                    continue;
+                }
+                Event::UpdateSimulation(value) => self.update_simulation = *value,
+                Event::RenderHeatMap(value) => self.render_heat_map = *value
+            }
+
+            applied.push(event.clone());
+        }
+
+        if let Some(recorder) = self.recorder.as_mut() {
+            recorder.record_frame(&applied, self.update_simulation, self.render_heat_map);
+        }
+
+        if self.update_simulation {
+            self.simulate();
+        }
+    }
+
+    fn step(&mut self, steps: u32) -> u32 {
+        for step in 0..steps {
+            if self.simulate() {
+                return step + 1;
+            }
+        }
+
+        steps
+    }
+
+    /// Runs one simulation tick, returns true if a breakpoint was hit
+    fn simulate(&mut self) -> bool {
+        self.cell_grid.update();
+
+        if self.statistics.enabled {
+            self.statistics.push(self.cell_grid.statistics());
+        }
+
+        self.check_breakpoints()
+    }
+
+    /// Pauses the simulation if a breakpoint was hit. Replays skip breakpoints, the pause is part of the recording.
+    fn check_breakpoints(&mut self) -> bool {
+        if self.replay.is_some() || self.breakpoints.is_empty() {
+            return false;
+        }
+
+        match breakpoint::check(&mut self.breakpoints, &self.cell_grid) {
+            Some(condition) => {
+                self.update_simulation = false;
+                self.breakpoint_message = format!("tick {}: {}", self.cell_grid.num_of_ticks(), condition.describe());
+                true
+            }
+            None => false
+        }
+    }
+
+    fn reload_stamps(&mut self) {
+        let (stamps, errors) = Stamp::load_all(STAMP_DIR);
+
+        self.stamps = stamps;
+        self.stamp_message = errors.join("\n");
+    }
+
+    fn save_clipboard(&mut self) {
+        let name = self.stamp_name.trim();
+
+        if let (Some(stamp), false) = (self.clipboard.as_mut(), name.is_empty()) {
+            stamp.name = name.to_string();
+
+            match stamp.save(STAMP_DIR) {
+                Ok(()) => self.reload_stamps(),
+                Err(err) => self.stamp_message = format!("failed to save {}: {}", name, err)
+            }
+        }
+    }
+
+    fn reload_keybindings(&mut self) {
+        let (keybindings, errors) = KeyBindings::load(KEYBINDINGS_PATH);
+
+        self.keybindings = keybindings;
+        self.keybinding_message = errors.join("\n");
+    }
+
+    fn start_recording(&mut self) {
+        self.replay = None;
+        self.history.clear();
+        self.recorder = Some(Recorder::start(&mut self.cell_grid));
+        self.replay_message = String::from("recording");
+    }
+
+    fn stop_recording(&mut self) {
+        if let Some(recorder) = self.recorder.take() {
+            let recording = recorder.finish(&self.cell_grid);
+
+            self.replay_message = match recording.save(RECORDING_PATH) {
+                Ok(()) => format!("saved {} frames to {}", recording.frames.len(), RECORDING_PATH),
+                Err(err) => format!("failed to save {}: {}", RECORDING_PATH, err)
+            };
+        }
+    }
+
+    fn load_replay(&mut self, path: &str) {
+        match Recording::load(path) {
+            Ok(recording) => self.start_replay(recording),
+            Err(err) => self.replay_message = format!("failed to load {}: {}", path, err)
+        }
+    }
+
+    fn start_replay(&mut self, recording: Recording) {
+        self.recorder = None;
+        self.replay_result = None;
+        self.history.clear();
+        self.replay = Some(Replay::start(recording, &mut self.cell_grid));
+        self.replay_message = String::from("replaying");
+    }
+
+    fn replay_frame(&mut self) {
+        let frame = match self.replay.as_mut() {
+            Some(replay) => replay.next_frame(),
+            None => return
+        };
+
+        if let Some(frame) = frame {
+            self.tick(&frame.events);
+        }
+
+        if let Some(replay) = self.replay.as_ref() {
+            if replay.is_finished() {
+                let result = replay.matches(&self.cell_grid);
+
+                self.replay_message = match result {
+                    Some(true) => String::from("replay finished, state matches"),
+                    Some(false) => String::from("replay finished, state differs"),
+                    None => String::from("replay finished")
+                };
+                self.replay_result = Some(result);
+                self.replay = None;
+            }
+        }
+    }
+}
+
+impl GameLogic for Game {
+    fn init(&mut self, ctx: &mut Context) -> Result<(), EngineError> {
+        let mut shapes = ShapeRenderer::new(ctx, 2048 * 4)?;
+        shapes.set_blend_mode(ctx, BlendMode::Alpha);
+
+        self.renderers = Some(Renderers {
+            grid: PixelRenderer::new(ctx, COLS as usize, ROWS as usize)?,
+            heat_map: PixelRenderer::new(ctx, COLS as usize, ROWS as usize)?,
+            shapes
+        });
+
+        if self.shader_watcher.is_some() {
+            self.reload_shaders(ctx);
+        }
         
         self.cell_grid.set_borders();
+        self.reload_stamps();
+        self.reload_keybindings();
+
+        let (width, height) = ctx.screen_size();
+        self.resize(width, height);
+        self.fit_grid();
+
+        Ok(())
+    }
+
+    fn resize(&mut self, width: f32, height: f32) {
+        self.window_size = Vec2::new(width, height);
+        // keeps the zoom and pan, `reset view` fits the grid again
+        self.camera.adjust_projection(width, height);
     }
 
     fn update(&mut self, info: &Info) {
-        if info.input.is_button_down(miniquad::MouseButton::Left) {
-            self.cell_grid.modify_cell((info.input.mouse_x  / self.cell_grid.get_size()) as i32 - self.brush_size / 2, grid::ROWS - (info.input.mouse_y  / self.cell_grid.get_size()) as i32 - self.brush_size / 2, self.selected_cell, self.brush_size);
+        self.move_camera(info);
+
+        if self.replay.is_some() {
+            self.ticks_last_frame = self.timestep.advance(info.perf.dt);
+
+            for _ in 0..self.ticks_last_frame {
+                self.replay_frame();
+            }
+            return;
         }
 
-        if self.update_simulation {
-            self.cell_grid.update();
+        let mut events = std::mem::take(&mut self.pending_events);
+
+        self.tool_events(info, &mut events);
+
+        self.hotkey_events(info, &mut events);
+
+        self.pending_events = events;
+
+        // while paused, edits still apply every frame so painting stays responsive
+        self.ticks_last_frame = if self.update_simulation {
+            self.timestep.advance(info.perf.dt)
+        } else {
+            self.timestep.reset();
+            1
+        };
+
+        for _ in 0..self.ticks_last_frame {
+            let events = std::mem::take(&mut self.pending_events);
+            self.tick(&events);
         }
     }
-    
+
     fn render(&mut self, ctx: &mut Context, bg_color: &mut (f32, f32, f32, f32)) {
         *bg_color = (0.13, 0.1, 0.11, 1f32);
 
+        if self.shader_watcher.as_mut().is_some_and(|watcher| watcher.poll()) {
+            self.reload_shaders(ctx);
+        }
 
-        let mut shape_renderer = self.shape_renderer.as_mut().unwrap();
-        shape_renderer.begin();
-        self.cell_grid.render(&mut shape_renderer);
-        shape_renderer.end(ctx, &mut self.camera);
+
+        let renderers = match self.renderers.as_mut() {
+            Some(renderers) => renderers,
+            None => return
+        };
+
+        let world_size = self.cell_grid.world_size();
+
+        self.cell_grid.render(renderers.grid.buffer());
+        renderers.grid.draw(ctx, &mut self.camera, 0f32, 0f32, world_size.x, world_size.y);
 
         if self.render_heat_map {
-            let mut heat_map_renderer = self.heat_map_renderer.as_mut().unwrap();
-            heat_map_renderer.begin();
-            self.cell_grid.render_heatmap(&mut heat_map_renderer);
-            heat_map_renderer.end(ctx, &mut self.camera);
+            if self.heat_map.auto_range {
+                let (min_heat, max_heat) = self.cell_grid.heat_range();
+                self.heat_map.auto_fit(min_heat, max_heat);
+            }
+
+            let heat_map_renderer = &mut renderers.heat_map;
+            if heat_map_renderer.get_blend_mode() != self.heat_map.blend_mode {
+                heat_map_renderer.set_blend_mode(ctx, self.heat_map.blend_mode);
+            }
+
+            self.cell_grid.render_heatmap(heat_map_renderer.buffer(), &self.heat_map);
+            heat_map_renderer.draw(ctx, &mut self.camera, 0f32, 0f32, world_size.x, world_size.y);
+        }
+
+        // overlays drawn on top of the grid
+        let shape_renderer = &mut renderers.shapes;
+        shape_renderer.begin();
+
+        let uses_brush = matches!(self.tool, Tool::Brush | Tool::Heat | Tool::SetTemperature);
+        let rect_tool = self.tool == Tool::Select || (uses_brush && self.brush_shape == BrushShape::Rectangle);
+        let dragging_rect = rect_tool && self.rect_start.is_some();
+
+        if let (true, Some((x0, y0))) = (rect_tool, self.rect_start) {
+            let (x1, y1) = self.last_mouse_cell;
+            let size = self.cell_grid.cell_size();
+
+            shape_renderer.set_color(1f32, 1f32, 1f32, 0.8);
+            shape_renderer.draw_rect_outline(x0.min(x1) as f32 * size, y0.min(y1) as f32 * size,
+                ((x1 - x0).abs() + 1) as f32 * size, ((y1 - y0).abs() + 1) as f32 * size, size / 4f32);
         }
+
+        // where the next click would paint, hidden while the mouse is over a window
+        if !self.pointer_over_ui && !dragging_rect {
+            let (x, y) = self.last_mouse_cell;
+            let size = self.cell_grid.cell_size();
+            let thickness = 2f32 / self.camera.get_zoom();
+
+            match (self.tool, self.secondary_stroke && self.painting) {
+                (Tool::Brush, true) => shape_renderer.set_color(1f32, 0.3, 0.3, 0.6),
+                (Tool::Heat, true) => shape_renderer.set_color(0.3, 0.6, 1f32, 0.6),
+                (Tool::Heat, false) => shape_renderer.set_color(1f32, 0.6, 0.2, 0.6),
+                _ => shape_renderer.set_color(1f32, 1f32, 1f32, 0.6)
+            }
+
+            match (self.tool, self.brush_shape, self.clipboard.as_ref()) {
+                (_, BrushShape::Square, _) if uses_brush => {
+                    let (x0, y0) = (x - self.brush_size / 2, y - self.brush_size / 2);
+                    shape_renderer.draw_rect_outline(x0 as f32 * size, y0 as f32 * size,
+                        self.brush_size as f32 * size, self.brush_size as f32 * size, thickness);
+                }
+                (_, BrushShape::Circle | BrushShape::Line, _) if uses_brush => {
+                    let radius = (self.brush_size as f32 / 2f32 + 0.5) * size;
+                    shape_renderer.draw_ring((x as f32 + 0.5) * size, (y as f32 + 0.5) * size, radius, thickness, 48);
+                }
+                (Tool::Paste, _, Some(stamp)) => {
+                    let (x0, y0) = (x - stamp.width() / 2, y - stamp.height() / 2);
+                    shape_renderer.draw_rect_outline(x0 as f32 * size, y0 as f32 * size,
+                        stamp.width() as f32 * size, stamp.height() as f32 * size, thickness);
+                }
+                _ => shape_renderer.draw_rect_outline(x as f32 * size, y as f32 * size, size, size, thickness)
+            }
+        }
+
+        shape_renderer.end(ctx, &mut self.camera);
+
+        self.draw_calls = 1 + self.render_heat_map as usize + shape_renderer.get_draw_calls();
     }
 
     fn render_egui(&mut self, info: &Info, egui_ctx: &egui::Context) {
-        egui::Window::new("window").title_bar(false).resizable(false).default_pos(Pos2::new(0.0, 750.0)).show(egui_ctx, |ui| {
+        // the control windows are pinned below the grid every frame so they follow resizes and `ui_panel_height`
+        let panel_top = self.window_size.y - self.ui_panel_height;
+
+        egui::Window::new("window").title_bar(false).resizable(false).fixed_pos(Pos2::new(0.0, panel_top)).show(egui_ctx, |ui| {
             ui.label("performance");
 
             ui.add_space(10f32);
 
-            ui.label(format!("Num of indices: {}", self.shape_renderer.as_ref().unwrap().get_num_indices()));
-            ui.label(format!("Num of vertices: {}", self.shape_renderer.as_ref().unwrap().get_num_vertices()));
-            ui.label(format!("Num of triangles: {}", self.shape_renderer.as_ref().unwrap().get_num_indices() / 3));
+            if let Some(renderers) = &self.renderers {
+                ui.label(format!("Num of texels: {}", renderers.grid.num_of_texels()));
+                ui.label(format!("Num of shape vertices: {}", renderers.shapes.get_num_vertices()));
+            }
+            ui.label(format!("Num of draw calls: {}", self.draw_calls));
 
             ui.label(format!("Num of swaps: {}", self.cell_grid.num_of_swaps()));
 
             ui.add_space(10f32);
             ui.label(format!("fps: {}", info.perf.fps));
+            ui.label(format!("ticks this frame: {}", self.ticks_last_frame));
         });
 
 
-        egui::Window::new("options").title_bar(false).resizable(false).default_pos(Pos2::new(0.0, 900.0)).show(egui_ctx, |ui| {
+        egui::Window::new("options").title_bar(false).resizable(false).fixed_pos(Pos2::new(0.0, panel_top + 150.0)).show(egui_ctx, |ui| {
             ui.label("options");
 
             ui.checkbox(&mut self.render_heat_map, "render_heat_map");
             ui.checkbox(&mut self.update_simulation, "update_simulation");
+            ui.checkbox(&mut self.show_statistics, "statistics");
+            ui.checkbox(&mut self.show_help, "keyboard shortcuts");
+
+            ui.add(egui::Slider::new(&mut self.timestep.ticks_per_second, 10f32..=240f32).integer().prefix("ticks_per_second: "));
+            ui.add(egui::Slider::new(&mut self.timestep.speed, 0.25f32..=8f32).logarithmic(true).prefix("speed: ").suffix("x"));
+            ui.add(egui::Slider::new(&mut self.timestep.max_steps, 1..=16).prefix("max_catch_up: "));
+
+            ui.horizontal(|ui| {
+                ui.label(format!("zoom: {:.2}", self.camera.get_zoom()));
+
+                if ui.button("reset view").clicked() {
+                    self.fit_grid();
+                }
+            });
+
+            if ui.add(egui::Slider::new(&mut self.ui_panel_height, 0f32..=600f32).integer().prefix("ui_panel_height: ")).changed() {
+                self.fit_grid();
+            }
+
+            ui.horizontal(|ui| {
+                if self.recorder.is_some() {
+                    if ui.button("stop recording").clicked() {
+                        self.stop_recording();
+                    }
+                } else if ui.button("record").clicked() {
+                    self.start_recording();
+                }
+
+                if ui.button("replay").clicked() {
+                    self.load_replay(RECORDING_PATH);
+                }
+            });
+
+            ui.horizontal(|ui| {
+                if ui.add_enabled(self.history.can_undo(), egui::Button::new("undo")).clicked() {
+                    self.pending_events.push(Event::Undo);
+                }
+
+                if ui.add_enabled(self.history.can_redo(), egui::Button::new("redo")).clicked() {
+                    self.pending_events.push(Event::Redo);
+                }
+
+                ui.label(format!("{} edits, {} KiB", self.history.num_of_edits(), self.history.memory_usage() / 1024));
+            });
+
+            if let Some(recorder) = self.recorder.as_ref() {
+                ui.label(format!("recording frame {}", recorder.num_of_frames()));
+            } else if let Some(replay) = self.replay.as_ref() {
+                let (frame, num_of_frames) = replay.progress();
+                ui.label(format!("replaying frame {} / {}", frame, num_of_frames));
+            } else if !self.replay_message.is_empty() {
+                ui.label(&self.replay_message);
+            }
         });
 
-        egui::Window::new("elements").title_bar(false).resizable(false).default_pos(Pos2::new(145.0, 750.0)).show(egui_ctx, |ui| {
+        let (mouse_i, mouse_j) = self.mouse_cell(&info.input);
+
+        egui::Window::new("elements").title_bar(false).resizable(false).fixed_pos(Pos2::new(145.0, panel_top)).show(egui_ctx, |ui| {
             
-            ui.label(format!("elements ({:?})", self.cell_grid.get_element_on_mouse((info.input.mouse_x  / self.cell_grid.get_size()) as i32, grid::ROWS - (info.input.mouse_y  / self.cell_grid.get_size()) as i32).unwrap_or(grid::CellType::Air)));
+            ui.label(format!("elements ({:?})", self.cell_grid.get_element_on_mouse(mouse_i, mouse_j).unwrap_or(grid::CellType::Air)));
             ui.separator();
 
             ui.horizontal_wrapped(|ui| {
@@ -163,12 +896,365 @@ impl  Game {
             });
         });
         
-        egui::Window::new("brush size").title_bar(false).resizable(false).default_pos(Pos2::new(145.0, 860.0)).show(egui_ctx, |ui| {
+        egui::Window::new("inspector").title_bar(false).resizable(false).default_pos(Pos2::new(self.window_size.x - 230.0, 0.0)).show(egui_ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.label(if self.pinned_cell.is_some() { "inspector (pinned)" } else { "inspector (hover, P to pin)" });
+
+                if self.pinned_cell.is_some() && ui.small_button("unpin").clicked() {
+                    self.pinned_cell = None;
+                }
+            });
+
+            ui.checkbox(&mut self.inspect_tooltip, "tooltip");
+
+            if let Some((i, j)) = self.pinned_cell.as_mut() {
+                ui.horizontal(|ui| {
+                    ui.add(egui::DragValue::new(i).clamp_range(0..=COLS - 1).prefix("x: "));
+                    ui.add(egui::DragValue::new(j).clamp_range(0..=ROWS - 1).prefix("y: "));
+                });
+            }
+
+            let (i, j) = self.pinned_cell.unwrap_or((mouse_i, mouse_j));
+            inspector::show(ui, &self.cell_grid, i, j);
+        });
+
+        if self.inspect_tooltip && !egui_ctx.is_pointer_over_area() {
+            if let Some(cell) = self.cell_grid.get_cell(mouse_i, mouse_j) {
+                egui::show_tooltip_at_pointer(egui_ctx, egui::Id::new("inspector tooltip"), |ui| {
+                    ui.label(format!("({}, {})", mouse_i, mouse_j));
+                    inspector::cell_fields(ui, &cell);
+                });
+            }
+        }
+
+        if self.render_heat_map {
+            egui::Window::new("heat map").title_bar(false).resizable(false).default_pos(Pos2::new(0.0, 0.0)).show(egui_ctx, |ui| {
+                ui.label("heat map");
+
+                egui::ComboBox::from_id_source("color map").selected_text(self.heat_map.color_map.name()).show_ui(ui, |ui| {
+                    for color_map in ColorMap::ALL {
+                        ui.selectable_value(&mut self.heat_map.color_map, color_map, color_map.name());
+                    }
+                });
+
+                ui.checkbox(&mut self.heat_map.auto_range, "auto range");
+
+                ui.add_enabled_ui(!self.heat_map.auto_range, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.add(egui::DragValue::new(&mut self.heat_map.min).speed(10f32).prefix("min: "));
+                        ui.add(egui::DragValue::new(&mut self.heat_map.max).speed(10f32).prefix("max: "));
+                    });
+                });
+                self.heat_map.max = self.heat_map.max.max(self.heat_map.min + 1f32);
+
+                ui.add(egui::Slider::new(&mut self.heat_map.opacity, 0f32..=1f32).prefix("opacity: "));
+
+                egui::ComboBox::from_id_source("heat map blend").selected_text(self.heat_map.blend_mode.name()).show_ui(ui, |ui| {
+                    for blend_mode in BlendMode::ALL {
+                        ui.selectable_value(&mut self.heat_map.blend_mode, blend_mode, blend_mode.name());
+                    }
+                });
+
+                let (rect, _) = ui.allocate_exact_size(egui::vec2(200f32, 16f32), egui::Sense::hover());
+                let steps = 64;
+                for step in 0..steps {
+                    let (r, g, b) = self.heat_map.color_map.sample((step as f32 + 0.5) / steps as f32);
+                    let x0 = rect.left() + rect.width() * step as f32 / steps as f32;
+                    let x1 = rect.left() + rect.width() * (step + 1) as f32 / steps as f32;
+
+                    ui.painter().rect_filled(egui::Rect::from_x_y_ranges(x0..=x1, rect.y_range()), 0f32, Color32::from_rgb((r * 255f32) as u8, (g * 255f32) as u8, (b * 255f32) as u8));
+                }
+
+                ui.horizontal(|ui| {
+                    ui.label(format!("{:.0}", self.heat_map.min));
+                    ui.add_space(60f32);
+                    ui.label(format!("{:.0}", (self.heat_map.min + self.heat_map.max) / 2f32));
+                    ui.add_space(60f32);
+                    ui.label(format!("{:.0}", self.heat_map.max));
+                });
+            });
+        }
+
+        if self.shader_watcher.is_some() {
+            egui::Window::new("shaders").title_bar(false).resizable(false).default_pos(Pos2::new(self.window_size.x / 2.0 - 150.0, 0.0)).show(egui_ctx, |ui| {
+                ui.label("shader dev mode, watching src/engine/vertex.glsl and fragment.glsl");
+
+                match &self.shader_error {
+                    Some(err) => ui.label(RichText::new(err).color(Color32::RED)),
+                    None => ui.label("shaders compiled")
+                };
+            });
+        }
+
+        egui::Window::new("keyboard shortcuts").open(&mut self.show_help).resizable(false).collapsible(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::vec2(0f32, 0f32)).show(egui_ctx, |ui| {
+            egui::Grid::new("keybindings").striped(true).show(ui, |ui| {
+                for (action, binding) in self.keybindings.bindings() {
+                    ui.label(action.description());
+                    ui.label(RichText::new(binding.map(|binding| binding.write()).unwrap_or_default()).monospace());
+                    ui.end_row();
+                }
+            });
+
+            ui.add_space(10f32);
+            ui.label(format!("bindings are read from {} at startup", KEYBINDINGS_PATH));
+
+            ui.horizontal(|ui| {
+                if ui.button("reload").clicked() {
+                    let (keybindings, errors) = KeyBindings::load(KEYBINDINGS_PATH);
+
+                    self.keybindings = keybindings;
+                    self.keybinding_message = errors.join("\n");
+                }
+
+                if ui.button("save").clicked() {
+                    self.keybinding_message = match self.keybindings.save(KEYBINDINGS_PATH) {
+                        Ok(()) => format!("saved {}", KEYBINDINGS_PATH),
+                        Err(err) => format!("failed to save {}: {}", KEYBINDINGS_PATH, err)
+                    };
+                }
+            });
+
+            if !self.keybinding_message.is_empty() {
+                ui.label(&self.keybinding_message);
+            }
+        });
+
+        egui::Window::new("statistics").open(&mut self.show_statistics).resizable(true).default_pos(Pos2::new(self.window_size.x - 460.0, 0.0)).show(egui_ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut self.statistics.enabled, "record");
+
+                if ui.button("clear").clicked() {
+                    self.statistics.clear();
+                }
+
+                if ui.button("export csv").clicked() {
+                    self.statistics_message = match self.statistics.save_csv(STATISTICS_PATH) {
+                        Ok(()) => format!("saved {} ticks to {}", self.statistics.samples().len(), STATISTICS_PATH),
+                        Err(err) => format!("failed to save {}: {}", STATISTICS_PATH, err)
+                    };
+                }
+            });
+
+            if !self.statistics_message.is_empty() {
+                ui.label(&self.statistics_message);
+            }
+
+            let latest = match self.statistics.latest() {
+                Some(latest) => latest,
+                None => {
+                    ui.label("no samples, enable record and run the simulation");
+                    return;
+                }
+            };
+
+            ui.label(format!("tick {}: heat min {:.1} / max {:.1} / mean {:.2}, total {:.0}, burning {}",
+                latest.tick, latest.min_heat, latest.max_heat, latest.mean_heat, latest.total_heat, latest.burning));
+
+            let samples = self.statistics.samples();
+            let series = |value: &dyn Fn(&Statistics) -> f64| plot::Values::from_values_iter(samples.iter().map(|sample| plot::Value::new(sample.tick as f64, value(sample))));
+
+            ui.label("element counts");
+            plot::Plot::new("element counts").height(150f32).legend(plot::Legend::default()).show(ui, |plot_ui| {
+                for cell_type in CellType::ALL {
+                    if cell_type != CellType::Air && cell_type != CellType::Solid && samples.iter().any(|sample| sample.count(cell_type) > 0) {
+                        plot_ui.line(plot::Line::new(series(&|sample| sample.count(cell_type) as f64)).name(format!("{:?}", cell_type)));
+                    }
+                }
+
+                plot_ui.line(plot::Line::new(series(&|sample| sample.burning as f64)).name("burning"));
+            });
+
+            ui.label("heat");
+            plot::Plot::new("heat").height(120f32).legend(plot::Legend::default()).show(ui, |plot_ui| {
+                plot_ui.line(plot::Line::new(series(&|sample| sample.min_heat as f64)).name("min"));
+                plot_ui.line(plot::Line::new(series(&|sample| sample.max_heat as f64)).name("max"));
+                plot_ui.line(plot::Line::new(series(&|sample| sample.mean_heat as f64)).name("mean"));
+            });
+
+            ui.label("heat histogram (cells per band, log10)");
+            plot::Plot::new("heat histogram").height(100f32).show(ui, |plot_ui| {
+                let bars = latest.heat_histogram.iter().enumerate()
+                    .map(|(bin, count)| plot::Bar::new(Statistics::bin_center(bin) as f64, (*count as f64 + 1f64).log10()).width(180f64))
+                    .collect();
+
+                plot_ui.bar_chart(plot::BarChart::new(bars));
+            });
+        });
+
+        egui::Window::new("simulation").title_bar(false).resizable(false).fixed_pos(Pos2::new(520.0, panel_top + 150.0)).show(egui_ctx, |ui| {
+            ui.label(format!("tick: {}", self.cell_grid.num_of_ticks()));
+
+            ui.horizontal(|ui| {
+                ui.add(egui::DragValue::new(&mut self.step_count).clamp_range(1..=10000).prefix("ticks: "));
+
+                if ui.button("step (.)").clicked() {
+                    self.pending_events.push(Event::Step(self.step_count));
+                }
+            });
+
+            ui.separator();
+            ui.label("breakpoints");
+
+            let mut removed = None;
+            for (index, breakpoint) in self.breakpoints.iter_mut().enumerate() {
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut breakpoint.enabled, breakpoint.condition.describe());
+
+                    if ui.small_button("x").clicked() {
+                        removed = Some(index);
+                    }
+                });
+            }
+            if let Some(index) = removed {
+                self.breakpoints.remove(index);
+            }
+
+            ui.horizontal(|ui| {
+                let (cell_type, x, y, count, heat) = match self.new_condition {
+                    Condition::CellAt { x, y, cell_type } => (cell_type, x, y, 1000, 1000f32),
+                    Condition::CountAbove { cell_type, count } => (cell_type, COLS / 2, ROWS / 2, count, 1000f32),
+                    Condition::MaxHeatAbove(heat) => (CellType::Fire, COLS / 2, ROWS / 2, 1000, heat)
+                };
+
+                egui::ComboBox::from_id_source("breakpoint condition").selected_text(self.new_condition.kind_name()).show_ui(ui, |ui| {
+                    for condition in [Condition::CellAt { x, y, cell_type }, Condition::CountAbove { cell_type, count }, Condition::MaxHeatAbove(heat)] {
+                        ui.selectable_value(&mut self.new_condition, condition, condition.kind_name());
+                    }
+                });
+
+                if ui.button("add").clicked() {
+                    self.breakpoints.push(Breakpoint::new(self.new_condition));
+                }
+            });
+
+            ui.horizontal(|ui| {
+                match &mut self.new_condition {
+                    Condition::CellAt { x, y, cell_type } => {
+                        cell_type_combo(ui, "breakpoint element", cell_type);
+                        ui.add(egui::DragValue::new(x).clamp_range(0..=COLS - 1).prefix("x: "));
+                        ui.add(egui::DragValue::new(y).clamp_range(0..=ROWS - 1).prefix("y: "));
+                    }
+                    Condition::CountAbove { cell_type, count } => {
+                        cell_type_combo(ui, "breakpoint element", cell_type);
+                        ui.add(egui::DragValue::new(count).prefix("count: "));
+                    }
+                    Condition::MaxHeatAbove(heat) => {
+                        ui.add(egui::DragValue::new(heat).prefix("heat: "));
+                    }
+                }
+            });
+
+            if !self.breakpoint_message.is_empty() {
+                ui.label(format!("hit {}", self.breakpoint_message));
+            }
+        });
+
+        egui::Window::new("stamps").title_bar(false).resizable(false).fixed_pos(Pos2::new(520.0, panel_top)).show(egui_ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.selectable_value(&mut self.tool, Tool::Brush, "brush");
+                ui.selectable_value(&mut self.tool, Tool::Heat, "heat");
+                ui.selectable_value(&mut self.tool, Tool::SetTemperature, "set temp");
+                ui.selectable_value(&mut self.tool, Tool::Select, "select");
+                ui.add_enabled_ui(self.clipboard.is_some(), |ui| {
+                    ui.selectable_value(&mut self.tool, Tool::Paste, "paste");
+                });
+            });
+
+            // left click heats and right click cools at this rate per second
+            match self.tool {
+                Tool::Heat => {
+                    ui.add(egui::DragValue::new(&mut self.heat_rate).clamp_range(0f32..=10000f32).prefix("heat per second: "));
+                }
+                Tool::SetTemperature => {
+                    ui.add(egui::DragValue::new(&mut self.temperature).prefix("temperature: "));
+                }
+                _ => ()
+            }
+
+            if let Some(stamp) = self.clipboard.as_mut() {
+                ui.label(format!("{} ({}x{})", stamp.name, stamp.width(), stamp.height()));
+
+                ui.horizontal(|ui| {
+                    if ui.button("rotate").clicked() {
+                        stamp.rotate();
+                    }
+
+                    if ui.button("flip h").clicked() {
+                        stamp.flip_horizontal();
+                    }
+
+                    if ui.button("flip v").clicked() {
+                        stamp.flip_vertical();
+                    }
+                });
+
+                ui.horizontal(|ui| {
+                    ui.add(egui::TextEdit::singleline(&mut self.stamp_name).hint_text("name").desired_width(80f32));
+
+                    if ui.button("save").clicked() {
+                        self.save_clipboard();
+                    }
+                });
+            }
+
+            ui.separator();
+
+            ui.horizontal_wrapped(|ui| {
+                for stamp in &self.stamps {
+                    if ui.button(&stamp.name).clicked() {
+                        self.clipboard = Some(stamp.clone());
+                        self.tool = Tool::Paste;
+                    }
+                }
+
+                if ui.button("reload").clicked() {
+                    self.reload_stamps();
+                }
+            });
+
+            if !self.stamp_message.is_empty() {
+                ui.label(&self.stamp_message);
+            }
+        });
+
+        egui::Window::new("brush size").title_bar(false).resizable(false).fixed_pos(Pos2::new(145.0, panel_top + 110.0)).show(egui_ctx, |ui| {
             ui.label("brush_size");
             
             ui.horizontal(|ui| {
-                ui.add(egui::Slider::new(&mut self.brush_size, 1..=50).integer().prefix("size: "));
+                ui.add(egui::Slider::new(&mut self.brush_size, 1..=MAX_BRUSH_SIZE).integer().prefix("size: "));
+            });
+
+            ui.horizontal_wrapped(|ui| {
+                for shape in BrushShape::ALL {
+                    ui.selectable_value(&mut self.brush_shape, shape, shape.name());
+                }
             });
+
+            ui.add(egui::Slider::new(&mut self.brush_modifiers.density, 0.01..=1.0).prefix("density: "));
+
+            ui.horizontal(|ui| {
+                egui::ComboBox::from_id_source("brush filter").selected_text(self.brush_modifiers.filter.name()).show_ui(ui, |ui| {
+                    for filter in [BrushFilter::ReplaceAll, BrushFilter::OnlyAir, BrushFilter::OnlyElement(self.filter_element), BrushFilter::EraseElement(self.filter_element)] {
+                        ui.selectable_value(&mut self.brush_modifiers.filter, filter, filter.name());
+                    }
+                });
+
+                cell_type_combo(ui, "filter element", &mut self.filter_element);
+            });
+
+            match &mut self.brush_modifiers.filter {
+                BrushFilter::OnlyElement(cell_type) | BrushFilter::EraseElement(cell_type) => *cell_type = self.filter_element,
+                _ => ()
+            }
         });
     }
-}
\ No newline at end of file
+}
+
+fn cell_type_combo(ui: &mut egui::Ui, id: &str, cell_type: &mut CellType) {
+    egui::ComboBox::from_id_source(id).selected_text(format!("{:?}", cell_type)).show_ui(ui, |ui| {
+        for option in CellType::ALL {
+            ui.selectable_value(cell_type, option, format!("{:?}", option));
+        }
+    });
+}
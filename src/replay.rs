@@ -0,0 +1,403 @@
+use std::{fs, io, path::Path};
+
+use crate::{brush::{Brush, BrushFilter, BrushModifiers, MAX_BRUSH_SIZE}, grid::{Cell, CellGrid, CellType, COLS, ROWS}, stamp::Stamp};
+
+pub const RECORDING_PATH: &str = "recording.txt";
+/// How many cells past the grid edge a recorded position may be, further than the mouse can reach in practice
+const MAX_OUTSIDE_GRID: i32 = 10_000;
+
+#[derive(Clone)]
+pub enum Event {
+    Paint { brush: Brush, modifiers: BrushModifiers, cell: Cell },
+    /// Adds `delta` to the heat under the brush, the element stays the same
+    Heat { brush: Brush, modifiers: BrushModifiers, delta: f32 },
+    /// Sets the heat under the brush, the element stays the same
+    SetTemperature { brush: Brush, modifiers: BrushModifiers, value: f32 },
+    Paste { x: i32, y: i32, stamp: Stamp },
+    /// Replaces every interior cell with air
+    Clear,
+    EndStroke,
+    Undo,
+    Redo,
+    /// Runs the simulation this many ticks on top of the regular update
+    Step(u32),
+    UpdateSimulation(bool),
+    RenderHeatMap(bool)
+}
+
+#[derive(Clone, Default)]
+pub struct Frame {
+    pub events: Vec<Event>
+}
+
+pub struct Recording {
+    pub seed: u64,
+    pub frames: Vec<Frame>,
+    pub checksum: Option<u64>
+}
+
+impl Recording {
+    /// One line per event, with a `frame` line starting every frame
+    pub fn write(&self) -> String {
+        let mut out = format!("seed {}\n", self.seed);
+
+        for frame in &self.frames {
+            out.push_str("frame\n");
+
+            for event in &frame.events {
+                match event {
+                    Event::Paint { brush, modifiers, cell } => out.push_str(&format!("paint {} {} {}\n", write_brush(brush), write_modifiers(modifiers), cell.write())),
+                    Event::Heat { brush, modifiers, delta } => out.push_str(&format!("heat {} {} {}\n", write_brush(brush), write_modifiers(modifiers), delta)),
+                    Event::SetTemperature { brush, modifiers, value } => out.push_str(&format!("set_temperature {} {} {}\n", write_brush(brush), write_modifiers(modifiers), value)),
+                    Event::Paste { x, y, stamp } => out.push_str(&format!("paste {} {} {}\n", x, y, stamp.write().replace('\n', " "))),
+                    Event::Clear => out.push_str("clear\n"),
+                    Event::EndStroke => out.push_str("end_stroke\n"),
+                    Event::Undo => out.push_str("undo\n"),
+                    Event::Redo => out.push_str("redo\n"),
+                    Event::Step(steps) => out.push_str(&format!("step {}\n", steps)),
+                    Event::UpdateSimulation(value) => out.push_str(&format!("update_simulation {}\n", value)),
+                    Event::RenderHeatMap(value) => out.push_str(&format!("render_heat_map {}\n", value))
+                }
+            }
+        }
+
+        if let Some(checksum) = self.checksum {
+            out.push_str(&format!("checksum {}\n", checksum));
+        }
+
+        out
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        fs::write(path, self.write())
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn parse(src: &str) -> io::Result<Self> {
+        let mut recording = Self { seed: 0, frames: Vec::new(), checksum: None };
+
+        for (line_num, line) in src.lines().enumerate() {
+            let mut parts = line.split_whitespace();
+            let invalid = || io::Error::new(io::ErrorKind::InvalidData, format!("invalid recording line {}: {:?}", line_num + 1, line));
+
+            let event = match parts.next() {
+                None => continue,
+                Some("seed") => {
+                    recording.seed = parse(parts.next()).ok_or_else(invalid)?;
+                    continue;
+                }
+                Some("checksum") => {
+                    recording.checksum = Some(parse(parts.next()).ok_or_else(invalid)?);
+                    continue;
+                }
+                Some("frame") => {
+                    recording.frames.push(Frame::default());
+                    continue;
+                }
+                Some("paint") => {
+                    let brush = parse_brush(&mut parts).ok_or_else(invalid)?;
+                    let modifiers = parse_modifiers(&mut parts).ok_or_else(invalid)?;
+                    let cell = Cell::parse(&mut parts).ok_or_else(invalid)?;
+
+                    Event::Paint { brush, modifiers, cell }
+                }
+                Some("heat") => {
+                    let brush = parse_brush(&mut parts).ok_or_else(invalid)?;
+                    let modifiers = parse_modifiers(&mut parts).ok_or_else(invalid)?;
+                    let delta = parse(parts.next()).ok_or_else(invalid)?;
+
+                    Event::Heat { brush, modifiers, delta }
+                }
+                Some("set_temperature") => {
+                    let brush = parse_brush(&mut parts).ok_or_else(invalid)?;
+                    let modifiers = parse_modifiers(&mut parts).ok_or_else(invalid)?;
+                    let value = parse(parts.next()).ok_or_else(invalid)?;
+
+                    Event::SetTemperature { brush, modifiers, value }
+                }
+                Some("paste") => {
+                    let x = parse_x(parts.next()).ok_or_else(invalid)?;
+                    let y = parse_y(parts.next()).ok_or_else(invalid)?;
+                    let stamp = Stamp::parse("paste", &mut parts).ok_or_else(invalid)?;
+
+                    Event::Paste { x, y, stamp }
+                }
+                Some("clear") => Event::Clear,
+                Some("end_stroke") => Event::EndStroke,
+                Some("undo") => Event::Undo,
+                Some("redo") => Event::Redo,
+                Some("step") => Event::Step(parse(parts.next()).ok_or_else(invalid)?),
+                Some("update_simulation") => Event::UpdateSimulation(parse(parts.next()).ok_or_else(invalid)?),
+                Some("render_heat_map") => Event::RenderHeatMap(parse(parts.next()).ok_or_else(invalid)?),
+                Some(_) => return Err(invalid())
+            };
+
+            recording.frames.last_mut().ok_or_else(invalid)?.events.push(event);
+        }
+
+        Ok(recording)
+    }
+}
+
+pub struct Recorder {
+    recording: Recording,
+    update_simulation: Option<bool>,
+    render_heat_map: Option<bool>
+}
+
+impl Recorder {
+    /// Seeds the rng and resets the grid so that the recording starts from a known state
+    pub fn start(cell_grid: &mut CellGrid) -> Self {
+        let seed = fastrand::u64(..);
+        fastrand::seed(seed);
+
+        cell_grid.clear();
+        cell_grid.set_borders();
+
+        Self {
+            recording: Recording { seed, frames: Vec::new(), checksum: None },
+            update_simulation: None,
+            render_heat_map: None
+        }
+    }
+
+    pub fn record_frame(&mut self, events: &[Event], update_simulation: bool, render_heat_map: bool) {
+        let mut frame = Frame::default();
+
+        if self.update_simulation != Some(update_simulation) {
+            self.update_simulation = Some(update_simulation);
+            frame.events.push(Event::UpdateSimulation(update_simulation));
+        }
+
+        if self.render_heat_map != Some(render_heat_map) {
+            self.render_heat_map = Some(render_heat_map);
+            frame.events.push(Event::RenderHeatMap(render_heat_map));
+        }
+
+        frame.events.extend_from_slice(events);
+
+        self.recording.frames.push(frame);
+    }
+
+    pub fn num_of_frames(&self) -> usize {
+        self.recording.frames.len()
+    }
+
+    pub fn finish(mut self, cell_grid: &CellGrid) -> Recording {
+        self.recording.checksum = Some(cell_grid.checksum());
+
+        self.recording
+    }
+}
+
+pub struct Replay {
+    recording: Recording,
+    next_frame: usize
+}
+
+impl Replay {
+    pub fn start(recording: Recording, cell_grid: &mut CellGrid) -> Self {
+        fastrand::seed(recording.seed);
+
+        cell_grid.clear();
+        cell_grid.set_borders();
+
+        Self { recording, next_frame: 0 }
+    }
+
+    pub fn next_frame(&mut self) -> Option<Frame> {
+        let frame = self.recording.frames.get(self.next_frame).cloned();
+        self.next_frame += 1;
+
+        frame
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.next_frame >= self.recording.frames.len()
+    }
+
+    pub fn progress(&self) -> (usize, usize) {
+        (self.next_frame.min(self.recording.frames.len()), self.recording.frames.len())
+    }
+
+    /// Returns `None` if the recording was saved without a checksum
+    pub fn matches(&self, cell_grid: &CellGrid) -> Option<bool> {
+        self.recording.checksum.map(|checksum| checksum == cell_grid.checksum())
+    }
+}
+
+fn parse<T: std::str::FromStr>(part: Option<&str>) -> Option<T> {
+    part?.parse().ok()
+}
+
+fn parse_x(part: Option<&str>) -> Option<i32> {
+    parse(part).filter(|x| (-MAX_OUTSIDE_GRID..COLS + MAX_OUTSIDE_GRID).contains(x))
+}
+
+fn parse_y(part: Option<&str>) -> Option<i32> {
+    parse(part).filter(|y| (-MAX_OUTSIDE_GRID..ROWS + MAX_OUTSIDE_GRID).contains(y))
+}
+
+fn parse_size(part: Option<&str>) -> Option<i32> {
+    parse(part).filter(|size| (1..=MAX_BRUSH_SIZE).contains(size))
+}
+
+fn write_brush(brush: &Brush) -> String {
+    match *brush {
+        Brush::Square { x, y, size } => format!("square {} {} {}", x, y, size),
+        Brush::Circle { x, y, size } => format!("circle {} {} {}", x, y, size),
+        Brush::Line { x0, y0, x1, y1, size } => format!("line {} {} {} {} {}", x0, y0, x1, y1, size),
+        Brush::Rectangle { x0, y0, x1, y1 } => format!("rectangle {} {} {} {}", x0, y0, x1, y1),
+        Brush::FloodFill { x, y } => format!("flood_fill {} {}", x, y)
+    }
+}
+
+fn parse_brush<'a>(parts: &mut impl Iterator<Item = &'a str>) -> Option<Brush> {
+    let brush = match parts.next()? {
+        "square" => Brush::Square { x: parse_x(parts.next())?, y: parse_y(parts.next())?, size: parse_size(parts.next())? },
+        "circle" => Brush::Circle { x: parse_x(parts.next())?, y: parse_y(parts.next())?, size: parse_size(parts.next())? },
+        "line" => Brush::Line { x0: parse_x(parts.next())?, y0: parse_y(parts.next())?, x1: parse_x(parts.next())?, y1: parse_y(parts.next())?, size: parse_size(parts.next())? },
+        "rectangle" => Brush::Rectangle { x0: parse_x(parts.next())?, y0: parse_y(parts.next())?, x1: parse_x(parts.next())?, y1: parse_y(parts.next())? },
+        "flood_fill" => Brush::FloodFill { x: parse_x(parts.next())?, y: parse_y(parts.next())? },
+        _ => return None
+    };
+
+    Some(brush)
+}
+
+fn write_modifiers(modifiers: &BrushModifiers) -> String {
+    let filter = match modifiers.filter {
+        BrushFilter::ReplaceAll => String::from("all"),
+        BrushFilter::OnlyAir => String::from("air"),
+        BrushFilter::OnlyElement(cell_type) => format!("only {}", cell_type as usize),
+        BrushFilter::EraseElement(cell_type) => format!("erase {}", cell_type as usize)
+    };
+
+    format!("{} {}", modifiers.density, filter)
+}
+
+fn parse_modifiers<'a>(parts: &mut impl Iterator<Item = &'a str>) -> Option<BrushModifiers> {
+    let density = parse(parts.next())?;
+
+    let filter = match parts.next()? {
+        "all" => BrushFilter::ReplaceAll,
+        "air" => BrushFilter::OnlyAir,
+        "only" => BrushFilter::OnlyElement(*CellType::ALL.get(parse::<usize>(parts.next())?)?),
+        "erase" => BrushFilter::EraseElement(*CellType::ALL.get(parse::<usize>(parts.next())?)?),
+        _ => return None
+    };
+
+    Some(BrushModifiers { density, filter })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::ElementData;
+
+    fn stamp() -> Stamp {
+        let mut cell_grid = CellGrid::new(1f32);
+        cell_grid.set_cell(5, 5, Cell::new(ElementData::water_element()));
+
+        Stamp::copy(&cell_grid, 4, 4, 6, 5)
+    }
+
+    fn recording() -> Recording {
+        let events = vec![
+            Event::UpdateSimulation(false),
+            Event::Paint {
+                brush: Brush::Circle { x: 10, y: 20, size: 5 },
+                modifiers: BrushModifiers { density: 0.5, filter: BrushFilter::OnlyElement(CellType::Water) },
+                cell: Cell::new(ElementData::sand_element())
+            },
+            Event::Paint {
+                brush: Brush::Rectangle { x0: 3, y0: 4, x1: 30, y1: 2 },
+                modifiers: BrushModifiers { density: 1f32, filter: BrushFilter::EraseElement(CellType::Coal) },
+                cell: Cell::default()
+            },
+            Event::Paint {
+                brush: Brush::FloodFill { x: 7, y: 8 },
+                modifiers: BrushModifiers { density: 1f32, filter: BrushFilter::OnlyAir },
+                cell: Cell::new(ElementData::lava_element())
+            },
+            Event::Heat { brush: Brush::Line { x0: 1, y0: 2, x1: 9, y1: 5, size: 3 }, modifiers: BrushModifiers::default(), delta: -12.5 },
+            Event::SetTemperature { brush: Brush::Square { x: 50, y: 60, size: 4 }, modifiers: BrushModifiers::default(), value: 401f32 },
+            Event::Paste { x: 30, y: 40, stamp: stamp() },
+            Event::EndStroke,
+            Event::Undo,
+            Event::Redo,
+            Event::Clear,
+            Event::Step(3),
+            Event::RenderHeatMap(true)
+        ];
+
+        Recording { seed: 42, frames: vec![Frame { events }, Frame::default()], checksum: Some(123) }
+    }
+
+    #[test]
+    fn round_trip_keeps_every_event() {
+        let recording = recording();
+        let parsed = Recording::parse(&recording.write()).unwrap();
+
+        assert_eq!(parsed.seed, 42);
+        assert_eq!(parsed.checksum, Some(123));
+        assert_eq!(parsed.frames.len(), 2);
+        assert_eq!(parsed.frames[0].events.len(), recording.frames[0].events.len());
+        assert!(parsed.frames[1].events.is_empty());
+        assert_eq!(parsed.write(), recording.write());
+    }
+
+    #[test]
+    fn round_trip_keeps_filters_and_pasted_stamps() {
+        let parsed = Recording::parse(&recording().write()).unwrap();
+        let events = &parsed.frames[0].events;
+
+        match &events[1] {
+            Event::Paint { brush, modifiers, cell } => {
+                assert_eq!(*brush, Brush::Circle { x: 10, y: 20, size: 5 });
+                assert_eq!(*modifiers, BrushModifiers { density: 0.5, filter: BrushFilter::OnlyElement(CellType::Water) });
+                assert_eq!(cell.element_data.cell_type, CellType::Sand);
+            }
+            _ => panic!("expected a paint event")
+        }
+
+        match &events[2] {
+            Event::Paint { modifiers, .. } => assert_eq!(modifiers.filter, BrushFilter::EraseElement(CellType::Coal)),
+            _ => panic!("expected a paint event")
+        }
+
+        match &events[6] {
+            Event::Paste { x, y, stamp } => {
+                assert_eq!((*x, *y), (30, 40));
+                assert_eq!((stamp.width(), stamp.height()), (3, 2));
+                assert_eq!(stamp.write(), self::stamp().write());
+            }
+            _ => panic!("expected a paste event")
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_lines() {
+        assert!(Recording::parse("seed 1\nframe\nexplode 3\n").is_err());
+        assert!(Recording::parse("seed 1\nundo\n").is_err());
+        assert!(Recording::parse("seed 1\nframe\npaint square 1 2\n").is_err());
+        assert!(Recording::parse("seed 1\nframe\npaste 0 0 100000 100000\n").is_err());
+        assert!(Recording::parse("seed 1\nframe\npaste 2147483647 0 1 1 0 0 0 0 0 0 0 0 0 false\n").is_err());
+    }
+
+    #[test]
+    fn rejects_brushes_far_outside_the_grid_or_too_large() {
+        let cell = Cell::default().write();
+
+        for brush in ["square 2147483647 5 5", "square 5 -2147483648 5", "circle 5 5 200000", "circle 5 5 0", "line 0 0 99999999 0 3", "rectangle 0 0 5 -50000"] {
+            let src = format!("seed 1\nframe\npaint {} 1 all {}\n", brush, cell);
+            assert!(Recording::parse(&src).is_err(), "{}", brush);
+        }
+
+        let src = format!("seed 1\nframe\npaint square -500 900 {} 1 all {}\n", MAX_BRUSH_SIZE, cell);
+        assert!(Recording::parse(&src).is_ok());
+    }
+}
@@ -0,0 +1,251 @@
+use std::{fs, io, path::Path};
+
+use crate::grid::{Cell, CellGrid, COLS, ROWS};
+
+pub const STAMP_DIR: &str = "stamps";
+const STAMP_EXTENSION: &str = "stamp";
+/// Largest stamp `parse` accepts, as many cells as the whole grid
+const MAX_STAMP_CELLS: i32 = COLS * ROWS;
+
+/// A rectangular copy of grid cells, including their heat and state
+#[derive(Clone)]
+pub struct Stamp {
+    pub name: String,
+    width: i32,
+    height: i32,
+    cells: Vec<Cell>
+}
+
+impl Stamp {
+    pub fn copy(cell_grid: &CellGrid, x0: i32, y0: i32, x1: i32, y1: i32) -> Self {
+        // corners are clamped to the grid so a selection dragged off it does not size the stamp by the outside area
+        let (min_x, min_y) = (x0.min(x1).clamp(0, COLS - 1), y0.min(y1).clamp(0, ROWS - 1));
+        let (max_x, max_y) = (x0.max(x1).clamp(0, COLS - 1), y0.max(y1).clamp(0, ROWS - 1));
+        let (width, height) = (max_x - min_x + 1, max_y - min_y + 1);
+
+        let mut cells = Vec::with_capacity((width * height) as usize);
+        for i in 0..width {
+            for j in 0..height {
+                cells.push(cell_grid.get_cell(min_x + i, min_y + j).unwrap_or_else(Cell::default));
+            }
+        }
+
+        Self { name: String::from("selection"), width, height, cells }
+    }
+
+    pub fn width(&self) -> i32 {
+        self.width
+    }
+
+    pub fn height(&self) -> i32 {
+        self.height
+    }
+
+    fn get(&self, i: i32, j: i32) -> Cell {
+        self.cells[(i * self.height + j) as usize]
+    }
+
+    /// Rotates a quarter turn clockwise
+    pub fn rotate(&mut self) {
+        let mut cells = Vec::with_capacity(self.cells.len());
+        for i in 0..self.height {
+            for j in 0..self.width {
+                cells.push(self.get(self.width - 1 - j, i));
+            }
+        }
+
+        std::mem::swap(&mut self.width, &mut self.height);
+        self.cells = cells;
+    }
+
+    pub fn flip_horizontal(&mut self) {
+        let (width, height) = (self.width, self.height);
+        self.cells = (0..width).flat_map(|i| (0..height).map(move |j| (i, j))).map(|(i, j)| self.get(width - 1 - i, j)).collect();
+    }
+
+    pub fn flip_vertical(&mut self) {
+        let (width, height) = (self.width, self.height);
+        self.cells = (0..width).flat_map(|i| (0..height).map(move |j| (i, j))).map(|(i, j)| self.get(i, height - 1 - j)).collect();
+    }
+
+    /// Grid positions and cells covered when the stamp is centered on `(x, y)`
+    pub fn placed_at(&self, x: i32, y: i32) -> Vec<((i32, i32), Cell)> {
+        let (x0, y0) = (x - self.width / 2, y - self.height / 2);
+
+        let mut placed = Vec::with_capacity(self.cells.len());
+        for i in 0..self.width {
+            for j in 0..self.height {
+                placed.push(((x0 + i, y0 + j), self.get(i, j)));
+            }
+        }
+
+        placed
+    }
+
+    /// Space separated `width height cell...`, read back by `Stamp::parse`
+    pub fn write(&self) -> String {
+        let mut out = format!("{} {}", self.width, self.height);
+
+        for cell in &self.cells {
+            out.push('\n');
+            out.push_str(&cell.write());
+        }
+
+        out
+    }
+
+    pub fn parse<'a>(name: &str, parts: &mut impl Iterator<Item = &'a str>) -> Option<Self> {
+        let width: i32 = parts.next()?.parse().ok()?;
+        let height: i32 = parts.next()?.parse().ok()?;
+
+        if width <= 0 || height <= 0 {
+            return None;
+        }
+
+        let len = width.checked_mul(height).filter(|&len| len <= MAX_STAMP_CELLS)?;
+
+        let cells = (0..len).map(|_| Cell::parse(parts)).collect::<Option<Vec<Cell>>>()?;
+
+        Some(Self { name: name.to_string(), width, height, cells })
+    }
+
+    pub fn save(&self, dir: impl AsRef<Path>) -> io::Result<()> {
+        fs::create_dir_all(&dir)?;
+
+        let file_name: String = self.name.chars().map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '_' }).collect();
+
+        fs::write(dir.as_ref().join(file_name).with_extension(STAMP_EXTENSION), self.write() + "\n")
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        let name = path.file_stem().map(|stem| stem.to_string_lossy().to_string()).unwrap_or_default();
+
+        let src = fs::read_to_string(path)?;
+
+        Self::parse(&name, &mut src.split_whitespace())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, format!("invalid stamp file {}", path.display())))
+    }
+
+    /// Loads every stamp file in `dir` sorted by name, along with the errors for the ones that failed
+    pub fn load_all(dir: impl AsRef<Path>) -> (Vec<Self>, Vec<String>) {
+        let mut stamps = Vec::new();
+        let mut errors = Vec::new();
+
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return (stamps, errors),
+            Err(err) => return (stamps, vec![err.to_string()])
+        };
+
+        for entry in entries.flatten() {
+            let path = entry.path();
+
+            if path.extension().is_some_and(|ext| ext == STAMP_EXTENSION) {
+                match Self::load(&path) {
+                    Ok(stamp) => stamps.push(stamp),
+                    Err(err) => errors.push(err.to_string())
+                }
+            }
+        }
+
+        stamps.sort_by(|a, b| a.name.cmp(&b.name));
+
+        (stamps, errors)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 3x2 stamp whose cells carry `10 * i + j` as heat, so moved cells can be told apart
+    fn numbered() -> Stamp {
+        let mut cell_grid = CellGrid::new(1f32);
+
+        for i in 0..3 {
+            for j in 0..2 {
+                let mut cell = Cell::default();
+                cell.heat_value = (10 * i + j) as f32;
+                cell_grid.set_cell(20 + i, 30 + j, cell);
+            }
+        }
+
+        Stamp::copy(&cell_grid, 22, 31, 20, 30)
+    }
+
+    fn heat(stamp: &Stamp, i: i32, j: i32) -> f32 {
+        stamp.get(i, j).heat_value
+    }
+
+    #[test]
+    fn copy_starts_at_the_lowest_corner() {
+        let stamp = numbered();
+
+        assert_eq!((stamp.width(), stamp.height()), (3, 2));
+        assert_eq!(heat(&stamp, 0, 0), 0f32);
+        assert_eq!(heat(&stamp, 2, 1), 21f32);
+    }
+
+    #[test]
+    fn copy_is_clamped_to_the_grid() {
+        let stamp = Stamp::copy(&CellGrid::new(1f32), -1_000_000, -5, 3, 2);
+
+        assert_eq!((stamp.width(), stamp.height()), (4, 3));
+        assert_eq!(stamp.cells.len(), 12);
+    }
+
+    #[test]
+    fn write_and_parse_round_trip() {
+        let stamp = numbered();
+        let parsed = Stamp::parse("numbered", &mut stamp.write().split_whitespace()).unwrap();
+
+        assert_eq!(parsed.name, "numbered");
+        assert_eq!((parsed.width(), parsed.height()), (3, 2));
+        assert_eq!(parsed.write(), stamp.write());
+    }
+
+    #[test]
+    fn parse_rejects_bad_sizes() {
+        let cell = Cell::default().write();
+
+        for header in ["0 2", "-1 3", "100000 100000", "2147483647 2"] {
+            assert!(Stamp::parse("bad", &mut format!("{} {}", header, cell).split_whitespace()).is_none(), "{}", header);
+        }
+
+        assert!(Stamp::parse("short", &mut format!("2 2 {} {}", cell, cell).split_whitespace()).is_none());
+    }
+
+    #[test]
+    fn rotate_turns_a_quarter_clockwise() {
+        let mut stamp = numbered();
+        stamp.rotate();
+
+        assert_eq!((stamp.width(), stamp.height()), (2, 3));
+        assert_eq!(heat(&stamp, 0, 0), 20f32);
+        assert_eq!(heat(&stamp, 1, 2), 1f32);
+
+        let original = numbered().write();
+        for _ in 0..3 {
+            stamp.rotate();
+        }
+        assert_eq!(stamp.write(), original);
+    }
+
+    #[test]
+    fn flips_mirror_and_undo_themselves() {
+        let mut stamp = numbered();
+
+        stamp.flip_horizontal();
+        assert_eq!(heat(&stamp, 0, 0), 20f32);
+        assert_eq!(heat(&stamp, 2, 1), 1f32);
+        stamp.flip_horizontal();
+        assert_eq!(stamp.write(), numbered().write());
+
+        stamp.flip_vertical();
+        assert_eq!(heat(&stamp, 0, 0), 1f32);
+        assert_eq!(heat(&stamp, 2, 1), 20f32);
+        stamp.flip_vertical();
+        assert_eq!(stamp.write(), numbered().write());
+    }
+}
@@ -0,0 +1,109 @@
+use std::{collections::VecDeque, fs, io, path::Path};
+
+use crate::grid::CellType;
+
+pub const STATISTICS_PATH: &str = "statistics.csv";
+
+pub const HEAT_BINS: usize = 20;
+pub const HEAT_MIN: f32 = -2000f32;
+pub const HEAT_MAX: f32 = 2000f32;
+
+/// Snapshot of the grid after one tick
+#[derive(Clone)]
+pub struct Statistics {
+    pub tick: u64,
+    pub counts: [u32; CellType::ALL.len()],
+    pub min_heat: f32,
+    pub max_heat: f32,
+    pub mean_heat: f32,
+    pub total_heat: f64,
+    /// Cells with `active` set, i.e. burning coal, saw dust and gasoline
+    pub burning: u32,
+    /// Cell counts per heat band between `HEAT_MIN` and `HEAT_MAX`, values outside land in the outer bins
+    pub heat_histogram: [u32; HEAT_BINS]
+}
+
+impl Statistics {
+    pub fn count(&self, cell_type: CellType) -> u32 {
+        self.counts[cell_type as usize]
+    }
+
+    pub fn heat_bin(heat: f32) -> usize {
+        let t = (heat - HEAT_MIN) / (HEAT_MAX - HEAT_MIN);
+
+        ((t * HEAT_BINS as f32) as isize).clamp(0, HEAT_BINS as isize - 1) as usize
+    }
+
+    /// Heat value at the center of a histogram bin
+    pub fn bin_center(bin: usize) -> f32 {
+        HEAT_MIN + (bin as f32 + 0.5) * (HEAT_MAX - HEAT_MIN) / HEAT_BINS as f32
+    }
+}
+
+/// Time series of statistics, dropping the oldest samples past `capacity`
+pub struct StatisticsLog {
+    pub enabled: bool,
+    samples: VecDeque<Statistics>,
+    capacity: usize
+}
+
+impl StatisticsLog {
+    pub fn new(capacity: usize) -> Self {
+        Self { enabled: false, samples: VecDeque::new(), capacity }
+    }
+
+    pub fn push(&mut self, statistics: Statistics) {
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+
+        self.samples.push_back(statistics);
+    }
+
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+
+    pub fn samples(&self) -> &VecDeque<Statistics> {
+        &self.samples
+    }
+
+    pub fn latest(&self) -> Option<&Statistics> {
+        self.samples.back()
+    }
+
+    /// One row per sample, the heat histogram bins are named after their center heat
+    pub fn to_csv(&self) -> String {
+        let mut out = String::from("tick");
+
+        for cell_type in CellType::ALL {
+            out.push_str(&format!(",{:?}", cell_type));
+        }
+        out.push_str(",min_heat,max_heat,mean_heat,total_heat,burning");
+
+        for bin in 0..HEAT_BINS {
+            out.push_str(&format!(",heat_{}", Statistics::bin_center(bin)));
+        }
+        out.push('\n');
+
+        for sample in &self.samples {
+            out.push_str(&sample.tick.to_string());
+
+            for count in sample.counts {
+                out.push_str(&format!(",{}", count));
+            }
+            out.push_str(&format!(",{},{},{},{},{}", sample.min_heat, sample.max_heat, sample.mean_heat, sample.total_heat, sample.burning));
+
+            for count in sample.heat_histogram {
+                out.push_str(&format!(",{}", count));
+            }
+            out.push('\n');
+        }
+
+        out
+    }
+
+    pub fn save_csv(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        fs::write(path, self.to_csv())
+    }
+}